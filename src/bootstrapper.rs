@@ -1,10 +1,16 @@
 pub mod init;
+mod mounts;
 mod prefix_sum_2d;
 pub mod shared_ctx;
+pub mod sync_output;
 mod test;
 pub use test::*;
 mod app;
 pub use app::*;
+mod exit_code;
+pub use exit_code::*;
+mod crash;
+pub use crash::*;
 
 use leptos_reactive::{create_runtime, create_scope, Scope};
 use std::{cell::OnceCell, error::Error, sync::Arc};
@@ -36,19 +42,32 @@ pub fn bootstrap<T: FnOnce(Scope) + 'static, B: Backend + 'static, I: Init<B> +
 
 #[cfg(test)]
 mod tests {
-    use leptos_reactive::{create_effect, use_context};
+    use std::{cell::RefCell, rc::Rc};
+
+    use crossterm::event::Event;
+    use leptos_reactive::{
+        create_effect, create_rw_signal, store_value, use_context, Signal, SignalGet,
+        SignalGetUntracked, SignalSet,
+    };
+    use tui::{backend::TestBackend, layout::Rect, widgets::Paragraph};
+
+    use crate::controlflow::show;
 
     use super::{
         assert_rb,
-        shared_ctx::{Quit, RenderBaseAuto},
-        test_bootstrap,
+        shared_ctx::{
+            min_size_guard, on_before_quit, on_shutdown, BeforeQuit, EventDispatcher, Quit,
+            QuitAny, RBOp, Region, RenderBase, RenderBaseAuto, RenderCounter,
+        },
+        sync_output::SyncOutput,
+        test_bootstrap, test_bootstrap_backend, RecordingBackend,
     };
 
     #[test]
     fn basic_init_then_quit() {
         test_bootstrap(
             |cx| {
-                let quit = use_context::<Quit>(cx).unwrap();
+                let quit = use_context::<QuitAny>(cx).unwrap();
 
                 let rb: RenderBaseAuto = use_context(cx).unwrap();
 
@@ -62,4 +81,330 @@ mod tests {
         )
         .unwrap();
     }
+
+    #[test]
+    fn layers_draw_in_order() {
+        test_bootstrap(
+            |cx| {
+                let rb: RenderBaseAuto = use_context(cx).unwrap();
+
+                {
+                    // Rendering only queues, so it is fine while the terminal is held
+                    let term = rb.access();
+                    let _term = term.try_lock().unwrap();
+
+                    rb.layer(1)
+                        .render(cx, Paragraph::new("over"), Rect::new(0, 0, 10, 1));
+                    rb.render(cx, Paragraph::new("base"), Rect::new(0, 0, 10, 2));
+                }
+
+                assert_rb(&rb, "layers");
+            },
+            10,
+            2,
+            Some(0),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn local_render_clips_to_region() {
+        test_bootstrap(
+            |cx| {
+                let rb: RenderBaseAuto = use_context(cx).unwrap();
+
+                cx.child_scope(|cx| {
+                    Region::derive(cx, Signal::derive(cx, || Rect::new(2, 1, 4, 2)));
+
+                    let rb = rb.local(cx);
+                    rb.render(cx, Paragraph::new("abcdefgh"), Rect::new(0, 0, 8, 1));
+                    rb.render(cx, Paragraph::new("ijklmnop"), Rect::new(1, 1, 8, 4));
+                });
+
+                // Offsets past the coordinate space are cut off, not overflowed
+                cx.child_scope(|cx| {
+                    Region::derive(cx, Signal::derive(cx, || Rect::new(u16::MAX - 5, 0, 5, 1)));
+
+                    let rb = rb.local(cx);
+                    rb.render(cx, Paragraph::new("hidden"), Rect::new(3, 0, 8, 1));
+                });
+
+                assert_rb(&rb, "local-clip");
+            },
+            10,
+            4,
+            Some(0),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn effects_keep_their_own_areas() {
+        test_bootstrap(
+            |cx| {
+                let cycle: RenderCounter = use_context(cx).unwrap();
+                let rb: RenderBaseAuto = use_context(cx).unwrap();
+
+                // Only the second effect re-renders, the first still shows
+                let (_, child) = cx.run_child_scope(|cx| {
+                    let once = rb.clone();
+                    create_effect(cx, move |_| {
+                        once.render(cx, Paragraph::new("AAAA"), Rect::new(0, 0, 4, 1))
+                    });
+
+                    let every = rb.clone();
+                    create_effect(cx, move |_| {
+                        if cycle.0.get() < 2 {
+                            every.render(cx, Paragraph::new("BBBB"), Rect::new(0, 1, 4, 1))
+                        }
+                    });
+                });
+                let child = store_value(cx, Some(child));
+
+                create_effect(cx, move |_| match cycle.0() {
+                    2 => child.update_value(|v| v.take().unwrap().dispose()),
+                    3 => assert_rb(&rb, "effects-unmount"),
+                    _ => (),
+                })
+            },
+            4,
+            2,
+            Some(4),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn offscreen_is_cached() {
+        test_bootstrap(
+            |cx| {
+                let when = create_rw_signal(cx, true);
+                let renders = store_value(cx, 0);
+                let cycle: RenderCounter = use_context(cx).unwrap();
+
+                let rb: RenderBaseAuto = use_context(cx).unwrap();
+
+                let area = Signal::derive(cx, || Rect::new(0, 0, 10, 1));
+                rb.offscreen(cx, area, move |cx| {
+                    let rb: RenderBaseAuto = use_context(cx).unwrap();
+                    create_effect(cx, move |_| {
+                        renders.update_value(|v| *v += 1);
+                        rb.render(cx, Paragraph::new("cached"), Rect::new(0, 0, 10, 1));
+                    });
+                });
+
+                let area = Signal::derive(cx, || Rect::new(0, 1, 10, 1));
+                let size = Signal::derive(cx, || (10, 1));
+                let offset = Signal::derive(cx, || (2, 0));
+                rb.scroll_view(cx, area, size, offset, move |cx| {
+                    let rb: RenderBaseAuto = use_context(cx).unwrap();
+                    rb.render(cx, Paragraph::new("scrolled"), Rect::new(0, 0, 10, 1));
+                });
+
+                show(
+                    cx,
+                    when,
+                    |cx| {
+                        let rb: RenderBaseAuto = use_context(cx).unwrap();
+                        create_effect(cx, move |_| {
+                            rb.layer(1)
+                                .render(cx, Paragraph::new("xx"), Rect::new(0, 0, 2, 1));
+                        })
+                    },
+                    |_| {},
+                );
+
+                create_effect(cx, move |_| match cycle.0() {
+                    0 => {
+                        assert_rb(&rb, "offscreen-0");
+                        when.set(false);
+                    }
+                    1 => {
+                        assert_rb(&rb, "offscreen-1");
+                        assert_eq!(renders.get_value(), 1);
+                    }
+                    _ => (),
+                })
+            },
+            10,
+            2,
+            Some(2),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn synchronized_frames() {
+        for mode in [SyncOutput::On, SyncOutput::Off] {
+            let (backend, rec) = RecordingBackend::new(10, 1);
+
+            test_bootstrap_backend(
+                move |cx| {
+                    let rb: RenderBase<RecordingBackend> = use_context(cx).unwrap();
+                    rb.synchronize(mode);
+                    rb.render(cx, Paragraph::new("hi"), Rect::new(0, 0, 10, 1));
+                },
+                backend,
+                Some(2),
+            )
+            .unwrap();
+
+            let out = String::from_utf8(rec.take()).unwrap();
+
+            match mode {
+                SyncOutput::On => {
+                    assert!(out.starts_with("\x1b[?2026h"));
+                    assert!(out.contains("hi"));
+                    assert!(out.ends_with("\x1b[?2026l"));
+                    // Frames without damage are not drawn at all
+                    assert_eq!(out.matches("\x1b[?2026h").count(), 1);
+                }
+                _ => {
+                    assert!(out.contains("hi"));
+                    assert!(!out.contains("\x1b[?2026"));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn quit_waits_on_guards() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let inner = Rc::clone(&log);
+
+        test_bootstrap(
+            move |cx| {
+                let quit = use_context::<Quit>(cx).unwrap();
+                let rc = use_context::<RenderCounter>(cx).unwrap();
+                let held = store_value(cx, None);
+
+                on_before_quit(cx, move |pending| {
+                    held.set_value(Some(pending));
+                    BeforeQuit::Defer
+                });
+                on_before_quit(cx, |_| BeforeQuit::Allow);
+
+                let log = Rc::clone(&inner);
+                on_shutdown(cx, move || log.borrow_mut().push("first"));
+                let log = Rc::clone(&inner);
+                on_shutdown(cx, move || log.borrow_mut().push("second"));
+
+                create_effect(cx, move |_| match rc.0.get() {
+                    0 => {
+                        quit.quit_with_message("bye");
+                        assert!(cx.untrack(|| quit.is_pending()));
+                        assert!(!quit.should_quit());
+                    }
+                    1 => {
+                        held.get_value().unwrap().cancel();
+                        assert!(!cx.untrack(|| quit.is_pending()));
+
+                        quit.quit_with_message("bye");
+                    }
+                    2 => held.get_value().unwrap().resume(),
+                    _ => panic!("quit was not resumed"),
+                });
+            },
+            10,
+            10,
+            Some(10),
+        )
+        .unwrap();
+
+        assert_eq!(*log.borrow(), ["second", "first"]);
+    }
+
+    #[test]
+    fn shutdown_runs_on_error() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let inner = Rc::clone(&log);
+        let (backend, rec) = RecordingBackend::new(10, 1);
+
+        let res = test_bootstrap_backend(
+            move |cx| {
+                let rb: RenderBase<RecordingBackend> = use_context(cx).unwrap();
+                let rc = use_context::<RenderCounter>(cx).unwrap();
+
+                on_shutdown(cx, move || inner.borrow_mut().push("shutdown"));
+
+                create_effect(cx, move |_| {
+                    if rc.0.get() == 1 {
+                        rec.fail();
+                        rb.render(cx, Paragraph::new("lost"), Rect::new(0, 0, 10, 1));
+                    }
+                });
+            },
+            backend,
+            Some(5),
+        );
+
+        assert!(res.is_err());
+        assert_eq!(*log.borrow(), ["shutdown"]);
+    }
+
+    #[test]
+    fn resize_blanks_the_frame() {
+        test_bootstrap(
+            |cx| {
+                let rb: RenderBaseAuto = use_context(cx).unwrap();
+                let rc = use_context::<RenderCounter>(cx).unwrap();
+                let ed = use_context::<EventDispatcher>(cx).unwrap();
+
+                // Drawn once without a mount, so nothing draws it again
+                rb.batch_render([
+                    RBOp::Component(Paragraph::new("0123456789"), Rect::new(0, 0, 10, 1)),
+                    RBOp::Component(Paragraph::new("abcdefghij"), Rect::new(0, 1, 10, 1)),
+                ]);
+                let app = rb.clone();
+                create_effect(cx, move |_| {
+                    app.render(cx, Paragraph::new("app"), Rect::new(0, 2, 3, 1))
+                });
+
+                create_effect(cx, move |_| match rc.0.get() {
+                    0 => ed.dispatch(Event::Resize(5, 3)).unwrap(),
+                    2 => assert_rb(&rb, "resize-shrink"),
+                    _ => (),
+                });
+            },
+            10,
+            3,
+            Some(3),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn resize_guards_min_size() {
+        test_bootstrap(
+            |cx| {
+                let rb: RenderBaseAuto = use_context(cx).unwrap();
+                let region: Region = use_context(cx).unwrap();
+                let rc = use_context::<RenderCounter>(cx).unwrap();
+                let ed = use_context::<EventDispatcher>(cx).unwrap();
+
+                let app = rb.clone();
+                create_effect(cx, move |_| {
+                    app.render(cx, Paragraph::new("app"), region());
+                });
+                min_size_guard::<TestBackend>(cx, (20, 3));
+
+                create_effect(cx, move |_| match rc.0.get() {
+                    0 => {
+                        // Only the last of a burst is applied, as (columns, rows)
+                        ed.dispatch(Event::Resize(30, 2)).unwrap();
+                        ed.dispatch(Event::Resize(18, 4)).unwrap();
+                    }
+                    2 => {
+                        assert_eq!(region.0.get_untracked(), Rect::new(0, 0, 18, 4));
+                        assert_rb(&rb, "min-size");
+                    }
+                    _ => (),
+                });
+            },
+            30,
+            4,
+            Some(3),
+        )
+        .unwrap();
+    }
 }
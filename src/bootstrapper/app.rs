@@ -1,60 +1,465 @@
-use super::{bootstrap, init::AppInit};
+use super::{
+    bootstrap,
+    init::AppInit,
+    shared_ctx::{GestureConfig, KeymapConfig, Stopped, UserConfig},
+    sync_output::SyncOutput,
+};
 use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture},
-    execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    cursor::{self, MoveTo},
+    event::{
+        DisableBracketedPaste, DisableFocusChange, DisableMouseCapture, EnableBracketedPaste,
+        EnableFocusChange, EnableMouseCapture, KeyCode, KeyEvent, KeyModifiers,
+        KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    queue,
+    style::Print,
+    terminal::{
+        self, disable_raw_mode, enable_raw_mode, Clear, ClearType, EnterAlternateScreen,
+        LeaveAlternateScreen, ScrollUp,
+    },
 };
 use leptos_reactive::Scope;
 use std::{
-    borrow::BorrowMut,
     error::Error,
-    io, panic,
+    io::{self, Write},
+    panic::{self, PanicHookInfo},
+    path::PathBuf,
     sync::{Arc, Mutex},
+    time::Duration,
+};
+use tui::{
+    backend::CrosstermBackend,
+    layout::Rect,
+    style::{Color, Style},
+    terminal::{TerminalOptions, Viewport},
+    Terminal,
 };
-use tui::{backend::CrosstermBackend, Terminal};
 
 use super::prefix_sum_2d::PrefixSum2d;
 
-pub fn app_bootstrap(boot: impl FnOnce(Scope) + 'static) -> Result<(), Box<dyn Error>> {
-    // setup terminal
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+/// Where the app is drawn
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Screen {
+    /// Takes over the whole terminal using the alternate screen
+    #[default]
+    Alternate,
+    /// A fixed number of rows at the cursor, left in the scrollback on exit
+    Inline(u16),
+}
+
+impl Screen {
+    /// The area drawn to on a terminal of `size`, keeping the rows of
+    /// `current` when inline
+    pub fn viewport(self, size: Rect, current: Rect) -> Rect {
+        match self {
+            Screen::Alternate => size,
+            Screen::Inline(h) => {
+                let h = std::cmp::min(h, size.height);
+                let y = std::cmp::min(current.y, size.height - h);
+
+                Rect::new(0, y, size.width, h)
+            }
+        }
+    }
+}
+
+/// The terminal features turned on while the app runs
+#[derive(Clone, Copy, Debug)]
+pub struct TerminalModes {
+    pub mouse: bool,
+    pub hide_cursor: bool,
+    pub bracketed_paste: bool,
+    pub focus_change: bool,
+    /// Pushed onto the kitty keyboard protocol stack, empty leaves the
+    /// legacy encoding
+    pub keyboard: KeyboardEnhancementFlags,
+}
+
+impl Default for TerminalModes {
+    fn default() -> Self {
+        Self {
+            mouse: true,
+            hide_cursor: true,
+            // Pastes arrive as one event instead of a key per character
+            bracketed_paste: true,
+            focus_change: true,
+            keyboard: KeyboardEnhancementFlags::empty(),
+        }
+    }
+}
+
+impl TerminalModes {
+    /// Drops the keyboard enhancements if the terminal does not answer the
+    /// query for them
+    pub fn detect(mut self) -> Self {
+        if self.keyboard.is_empty() {
+            return self;
+        }
+
+        // The answer is only read back in raw mode
+        let supported = enable_raw_mode()
+            .and_then(|_| terminal::supports_keyboard_enhancement())
+            .unwrap_or(false);
+        let _ = disable_raw_mode();
+
+        if !supported {
+            self.keyboard = KeyboardEnhancementFlags::empty();
+        }
+        self
+    }
+
+    /// Takes over the terminal, an inline viewport is reserved separately
+    pub fn enter(self, w: &mut impl Write, screen: Screen) -> io::Result<()> {
+        enable_raw_mode()?;
+
+        if screen == Screen::Alternate {
+            queue!(w, EnterAlternateScreen)?;
+        }
+        if self.mouse {
+            queue!(w, EnableMouseCapture)?;
+        }
+        if self.bracketed_paste {
+            queue!(w, EnableBracketedPaste)?;
+        }
+        if self.focus_change {
+            queue!(w, EnableFocusChange)?;
+        }
+        // After switching screens, as each has its own stack
+        if !self.keyboard.is_empty() {
+            queue!(w, PushKeyboardEnhancementFlags(self.keyboard))?;
+        }
+        if self.hide_cursor {
+            queue!(w, cursor::Hide)?;
+        }
+
+        w.flush()
+    }
+
+    /// Hands the terminal back, `area` being the last viewport
+    pub fn restore(self, w: &mut impl Write, screen: Screen, area: Rect) -> io::Result<()> {
+        disable_raw_mode()?;
+
+        if !self.keyboard.is_empty() {
+            queue!(w, PopKeyboardEnhancementFlags)?;
+        }
+        match screen {
+            Screen::Alternate => queue!(w, LeaveAlternateScreen)?,
+            // Leaves the last frame in the scrollback and continues below it
+            Screen::Inline(_) => {
+                queue!(w, MoveTo(0, area.bottom().saturating_sub(1)), Print("\r\n"))?
+            }
+        }
+        if self.mouse {
+            queue!(w, DisableMouseCapture)?;
+        }
+        if self.bracketed_paste {
+            queue!(w, DisableBracketedPaste)?;
+        }
+        if self.focus_change {
+            queue!(w, DisableFocusChange)?;
+        }
+        queue!(w, cursor::Show)?;
+
+        w.flush()
+    }
+}
+
+/// The parts of an [`AppBuilder`] used by the main-loop
+#[derive(Clone, Debug)]
+pub struct AppConfig {
+    pub screen: Screen,
+    pub modes: TerminalModes,
+    pub tick_rate: Duration,
+    pub sync_output: SyncOutput,
 
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
-    let ps = PrefixSum2d::new(terminal.size()?);
+    /// How long to wait for more resizes before applying one
+    pub resize_debounce: Duration,
+    /// The smallest `(width, height)` the app is shown at
+    pub min_size: Option<(u16, u16)>,
+    /// The style focused components draw their ring with
+    pub focus_ring: Style,
+    pub gestures: GestureConfig,
+    pub keymap: KeymapConfig,
+    /// The TOML file keys and styles are read from
+    pub user_config: Option<PathBuf>,
 
-    terminal.hide_cursor()?;
-    terminal.clear()?;
+    /// Where input is recorded to, see [`record`](super::shared_ctx::record)
+    #[cfg(feature = "record")]
+    pub record: Option<PathBuf>,
+    /// A recording played back on start and how much faster
+    #[cfg(feature = "record")]
+    pub replay: Option<(PathBuf, f64)>,
 
-    let terminal = Arc::new(Mutex::new((terminal, ps)));
+    /// The key toggling the console, `None` disables it
+    pub console: Option<KeyCode>,
+    /// The key suspending the app like a shell job, `None` disables it
+    pub suspend: Option<KeyEvent>,
+}
 
-    panic::set_hook(Box::new(|c| {
-        disable_raw_mode().unwrap();
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            screen: Screen::Alternate,
+            modes: Default::default(),
+            tick_rate: Duration::from_millis(10),
+            sync_output: SyncOutput::Auto,
+            resize_debounce: Duration::from_millis(20),
+            min_size: None,
+            focus_ring: Style::default().fg(Color::Yellow),
+            gestures: Default::default(),
+            keymap: Default::default(),
+            user_config: None,
+            #[cfg(feature = "record")]
+            record: None,
+            #[cfg(feature = "record")]
+            replay: None,
+            console: Some(KeyCode::F(12)),
+            suspend: Some(KeyEvent::new(KeyCode::Char('z'), KeyModifiers::CONTROL)),
+        }
+    }
+}
 
-        println!("{}", c);
-    }));
+type PanicHook = Box<dyn Fn(&PanicHookInfo) + Send + Sync + 'static>;
 
-    let res = bootstrap(terminal.clone(), boot, AppInit::default());
+pub struct AppBuilder {
+    config: AppConfig,
 
-    let mut terminal = Arc::try_unwrap(terminal)
-        .unwrap_or_else(|_| panic!("Terminal leaked in main-loop"))
-        .into_inner()?
-        .0;
+    panic: Option<PanicHook>,
+    crash_dir: Option<PathBuf>,
+}
 
-    // restore terminal
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    terminal.show_cursor()?;
+impl Default for AppBuilder {
+    fn default() -> Self {
+        Self {
+            config: Default::default(),
 
-    match res?.0 {
-        Some(v) if v.len() > 0 => println!("{}", v),
-        _ => (),
+            panic: None,
+            crash_dir: Some(std::env::temp_dir()),
+        }
     }
-    Ok(())
+}
+
+impl AppBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn screen(mut self, screen: Screen) -> Self {
+        self.config.screen = screen;
+        self
+    }
+
+    pub fn inline(self, height: u16) -> Self {
+        self.screen(Screen::Inline(height))
+    }
+
+    pub fn mouse(mut self, enabled: bool) -> Self {
+        self.config.modes.mouse = enabled;
+        self
+    }
+
+    pub fn hide_cursor(mut self, hidden: bool) -> Self {
+        self.config.modes.hide_cursor = hidden;
+        self
+    }
+
+    pub fn bracketed_paste(mut self, enabled: bool) -> Self {
+        self.config.modes.bracketed_paste = enabled;
+        self
+    }
+
+    pub fn focus_change(mut self, enabled: bool) -> Self {
+        self.config.modes.focus_change = enabled;
+        self
+    }
+
+    /// Asks for the kitty keyboard protocol, which tells apart keys like
+    /// `Ctrl-i` and `Tab` and reports repeats and releases. Terminals without
+    /// it keep the legacy encoding.
+    pub fn keyboard_enhancement(mut self, flags: KeyboardEnhancementFlags) -> Self {
+        self.config.modes.keyboard = flags;
+        self
+    }
+
+    pub fn console(mut self, toggle: Option<KeyCode>) -> Self {
+        self.config.console = toggle;
+        self
+    }
+
+    pub fn suspend(mut self, key: Option<KeyEvent>) -> Self {
+        self.config.suspend = key;
+        self
+    }
+
+    pub fn tick_rate(mut self, tick_rate: Duration) -> Self {
+        self.config.tick_rate = tick_rate;
+        self
+    }
+
+    pub fn resize_debounce(mut self, debounce: Duration) -> Self {
+        self.config.resize_debounce = debounce;
+        self
+    }
+
+    /// Shows a notice instead of the app while the terminal is smaller
+    pub fn min_size(mut self, width: u16, height: u16) -> Self {
+        self.config.min_size = Some((width, height));
+        self
+    }
+
+    pub fn focus_ring(mut self, style: Style) -> Self {
+        self.config.focus_ring = style;
+        self
+    }
+
+    pub fn gestures(mut self, config: GestureConfig) -> Self {
+        self.config.gestures = config;
+        self
+    }
+
+    pub fn keymap(mut self, config: KeymapConfig) -> Self {
+        self.config.keymap = config;
+        self
+    }
+
+    /// Loads keys and styles from `config.toml` in the XDG config directory
+    /// of `app`, see [`UserConfig`]
+    pub fn user_config(mut self, app: &str) -> Self {
+        self.config.user_config = UserConfig::xdg_path(app);
+        self
+    }
+
+    /// Records every input event to `path`, to reproduce a session later
+    #[cfg(feature = "record")]
+    pub fn record(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.record = Some(path.into());
+        self
+    }
+
+    /// Plays back a recorded session on start, `speed` times faster, next to
+    /// any live input. A `speed` that is not positive plays it as recorded.
+    #[cfg(feature = "record")]
+    pub fn replay(mut self, path: impl Into<PathBuf>, speed: f64) -> Self {
+        self.config.replay = Some((path.into(), speed));
+        self
+    }
+
+    pub fn sync_output(mut self, mode: SyncOutput) -> Self {
+        self.config.sync_output = mode;
+        self
+    }
+
+    /// Called with the panic once the terminal is restored, before the
+    /// previously installed hook
+    pub fn on_panic(mut self, hook: impl Fn(&PanicHookInfo) + Send + Sync + 'static) -> Self {
+        self.panic = Some(Box::new(hook));
+        self
+    }
+
+    /// Where crash reports are written on panic, `None` disables them
+    pub fn crash_report(mut self, dir: Option<PathBuf>) -> Self {
+        self.crash_dir = dir;
+        self
+    }
+
+    pub fn init<T>(&self) -> AppInit<T> {
+        AppInit::new(self.config.clone())
+    }
+
+    /// Runs the app until it quits with a `T`, see [`Quit`](super::shared_ctx::Quit).
+    /// Being stopped by a signal returns [`Stopped::Signal`] with the
+    /// [`TermSignal`](super::shared_ctx::TermSignal).
+    pub fn run<T: 'static>(
+        mut self,
+        boot: impl FnOnce(Scope) + 'static,
+    ) -> Result<Stopped<T>, Box<dyn Error>> {
+        self.config.modes = self.config.modes.detect();
+        let (screen, modes) = (self.config.screen, self.config.modes);
+
+        // setup terminal
+        let mut stdout = io::stdout();
+        modes.enter(&mut stdout, screen)?;
+
+        let area = match screen {
+            Screen::Alternate => {
+                let (w, h) = terminal::size()?;
+                Rect::new(0, 0, w, h)
+            }
+            Screen::Inline(h) => {
+                let (w, rows) = terminal::size()?;
+                let (_, row) = cursor::position()?;
+                let h = std::cmp::min(h, rows);
+
+                // Scrolls the content above the cursor up until the viewport fits
+                let overflow = (row + h).saturating_sub(rows);
+                if overflow > 0 {
+                    queue!(stdout, ScrollUp(overflow))?;
+                }
+
+                let area = Rect::new(0, row - overflow, w, h);
+                queue!(stdout, MoveTo(0, area.y), Clear(ClearType::FromCursorDown))?;
+                stdout.flush()?;
+
+                area
+            }
+        };
+
+        let backend = CrosstermBackend::new(stdout);
+        let mut terminal = Terminal::with_options(
+            backend,
+            TerminalOptions {
+                viewport: Viewport::fixed(area),
+            },
+        )?;
+        let ps = PrefixSum2d::new(area);
+
+        if screen == Screen::Alternate {
+            terminal.clear()?;
+        }
+
+        let terminal = Arc::new(Mutex::new((terminal, ps)));
+
+        let init = self.init();
+        let log = init.crash_log();
+
+        let prev: Arc<dyn Fn(&PanicHookInfo) + Send + Sync> = Arc::from(panic::take_hook());
+        let chained = Arc::clone(&prev);
+        let (hook, dir) = (self.panic, self.crash_dir);
+
+        panic::set_hook(Box::new(move |c| {
+            // The terminal lock may be held, so stdout is used directly
+            let _ = modes.restore(&mut io::stdout(), screen, area);
+
+            if let Some(dir) = &dir {
+                match log.write_report(dir, c) {
+                    Ok(path) => eprintln!("Crash report written to {}", path.display()),
+                    Err(e) => eprintln!("Failed to write crash report: {}", e),
+                }
+            }
+            if let Some(hook) = &hook {
+                hook(c);
+            }
+
+            chained(c);
+        }));
+
+        let res = bootstrap(terminal.clone(), boot, init);
+
+        let _ = panic::take_hook();
+        panic::set_hook(Box::new(move |c| prev(c)));
+
+        let (mut terminal, ps) = Arc::try_unwrap(terminal)
+            .unwrap_or_else(|_| panic!("Terminal leaked in main-loop"))
+            .into_inner()?;
+
+        modes.restore(terminal.backend_mut(), screen, ps.area())?;
+
+        res?.0.ok_or_else(|| "App stopped without quitting".into())
+    }
+}
+
+pub fn app_bootstrap<T: 'static>(
+    boot: impl FnOnce(Scope) + 'static,
+) -> Result<Stopped<T>, Box<dyn Error>> {
+    AppBuilder::default().run(boot)
 }
@@ -0,0 +1,147 @@
+use std::{
+    backtrace::Backtrace,
+    collections::VecDeque,
+    fmt::{Display, Write as _},
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, Local};
+use leptos_reactive::*;
+
+use super::shared_ctx::{Console, EventQueue};
+
+/// How many console entries and events make it into a crash report
+const KEEP: usize = 32;
+
+#[derive(Default)]
+struct Recent {
+    console: VecDeque<(DateTime<Local>, String)>,
+    events: VecDeque<(DateTime<Local>, String)>,
+}
+
+/// The recent history of the app, kept outside of the reactive runtime so
+/// the panic hook can still read it
+#[derive(Clone, Default)]
+pub struct CrashLog(Arc<Mutex<Recent>>);
+
+impl CrashLog {
+    /// Mirrors the [`Console`] and [`EventQueue`] of `cx`
+    pub fn attach(&self, cx: Scope) {
+        provide_context(cx, self.clone());
+
+        if let Some(console) = use_context::<Console>(cx) {
+            let log = self.clone();
+            create_effect(cx, move |_| {
+                console.with(|v| {
+                    let recent = v.iter().rev().take(KEEP).rev().cloned().collect();
+                    log.update(|r| r.console = recent);
+                })
+            });
+        }
+
+        if let Some(eq) = use_context::<EventQueue>(cx) {
+            let log = self.clone();
+            eq.listen(cx, move |e| {
+                log.update(|r| {
+                    if r.events.len() == KEEP {
+                        r.events.pop_front();
+                    }
+                    r.events.push_back((Local::now(), format!("{:?}", e)));
+                })
+            });
+        }
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Recent)) {
+        // A panic while holding the lock leaves the history usable
+        let mut recent = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        f(&mut recent)
+    }
+
+    /// The report for `panic`, with the recent console entries and events
+    pub fn report(&self, panic: &dyn Display, backtrace: &Backtrace) -> String {
+        let mut out = String::new();
+        let recent = self.0.lock().unwrap_or_else(|e| e.into_inner());
+
+        let section = |out: &mut String, title, entries: &VecDeque<(DateTime<Local>, String)>| {
+            let _ = writeln!(out, "\n== {}", title);
+            for (at, v) in entries {
+                let _ = writeln!(out, "[{}] {}", at.format("%H:%M:%S%.3f"), v);
+            }
+        };
+
+        let _ = writeln!(
+            out,
+            "{} crashed at {}",
+            env!("CARGO_PKG_NAME"),
+            Local::now()
+        );
+        let _ = writeln!(out, "\n{}", panic);
+        let _ = writeln!(out, "\n== Backtrace\n{}", backtrace);
+        section(&mut out, "Console", &recent.console);
+        section(&mut out, "Events", &recent.events);
+
+        out
+    }
+
+    /// Writes the report for `panic` into `dir`, returning its path
+    pub fn write_report(&self, dir: &Path, panic: &dyn Display) -> io::Result<PathBuf> {
+        let path = dir.join(format!(
+            "{}-crash-{}.log",
+            env!("CARGO_PKG_NAME"),
+            Local::now().format("%Y%m%d-%H%M%S")
+        ));
+
+        let report = self.report(panic, &Backtrace::force_capture());
+        fs::File::create(&path)?.write_all(report.as_bytes())?;
+
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::backtrace::Backtrace;
+
+    use crossterm::event::Event;
+    use leptos_reactive::use_context;
+
+    use crate::bootstrapper::{
+        shared_ctx::{Console, EventDispatcher},
+        test_bootstrap,
+    };
+
+    use super::CrashLog;
+
+    #[test]
+    fn report_keeps_history() {
+        let log = CrashLog::default();
+        let inner = log.clone();
+
+        test_bootstrap(
+            move |cx| {
+                let console = Console::attach(cx);
+                inner.attach(cx);
+
+                console.clear();
+                console.log("about to fail");
+
+                let ed = use_context::<EventDispatcher>(cx).unwrap();
+                ed.dispatch(Event::FocusLost).unwrap();
+            },
+            10,
+            10,
+            Some(3),
+        )
+        .unwrap();
+
+        let report = log.report(&"boom", &Backtrace::disabled());
+
+        assert!(report.contains("\nboom\n"));
+        assert!(report.contains("] about to fail\n"));
+        assert!(report.contains("] FocusLost\n"));
+    }
+}
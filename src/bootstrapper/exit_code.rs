@@ -0,0 +1,123 @@
+use std::{error::Error, process::ExitCode};
+
+use super::shared_ctx::Stopped;
+
+/// Maps the result of an app to the exit code of the process, without
+/// printing anything
+pub trait ToExitCode {
+    fn to_exit_code(&self) -> ExitCode;
+}
+
+impl ToExitCode for ExitCode {
+    fn to_exit_code(&self) -> ExitCode {
+        *self
+    }
+}
+
+impl ToExitCode for () {
+    fn to_exit_code(&self) -> ExitCode {
+        ExitCode::SUCCESS
+    }
+}
+
+impl ToExitCode for u8 {
+    fn to_exit_code(&self) -> ExitCode {
+        ExitCode::from(*self)
+    }
+}
+
+impl ToExitCode for bool {
+    fn to_exit_code(&self) -> ExitCode {
+        if *self {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        }
+    }
+}
+
+impl ToExitCode for String {
+    fn to_exit_code(&self) -> ExitCode {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Nothing selected is a failure, like `grep` finding no match
+impl<T: ToExitCode> ToExitCode for Option<T> {
+    fn to_exit_code(&self) -> ExitCode {
+        match self {
+            Some(v) => v.to_exit_code(),
+            None => ExitCode::FAILURE,
+        }
+    }
+}
+
+/// Dismissed without a result, like an empty [`Option`]
+impl<T: ToExitCode> ToExitCode for Stopped<T> {
+    fn to_exit_code(&self) -> ExitCode {
+        match self {
+            Self::Quit(v) => v.to_exit_code(),
+            Self::Dismissed => ExitCode::FAILURE,
+            Self::Signal(s) => s.exit_code(),
+        }
+    }
+}
+
+/// Any error fails, a signal ends the app with [`Stopped::Signal`] instead
+impl<T: ToExitCode> ToExitCode for Result<T, Box<dyn Error>> {
+    fn to_exit_code(&self) -> ExitCode {
+        match self {
+            Ok(v) => v.to_exit_code(),
+            Err(_) => ExitCode::FAILURE,
+        }
+    }
+}
+
+/// The exit code of `res`, handing the value to `f` when there is one and
+/// printing the error to stderr otherwise
+pub fn exit_with<T: ToExitCode>(res: Result<T, Box<dyn Error>>, f: impl FnOnce(T)) -> ExitCode {
+    let code = res.to_exit_code();
+
+    match res {
+        Ok(v) => f(v),
+        Err(e) => eprintln!("{}", e),
+    }
+
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{error::Error, process::ExitCode};
+
+    use super::{
+        super::shared_ctx::{Stopped, TermSignal},
+        exit_with, ToExitCode,
+    };
+
+    #[test]
+    fn maps_results() {
+        let ok: Result<Option<u8>, Box<dyn Error>> = Ok(Some(3));
+        let none: Result<Option<u8>, Box<dyn Error>> = Ok(None);
+        let dismissed: Result<Stopped<u8>, Box<dyn Error>> = Ok(Stopped::Dismissed);
+        let err: Result<(), Box<dyn Error>> = Err("failed".into());
+
+        assert_eq!(ok.to_exit_code(), ExitCode::from(3));
+        assert_eq!(none.to_exit_code(), ExitCode::FAILURE);
+        assert_eq!(dismissed.to_exit_code(), ExitCode::FAILURE);
+        assert_eq!(
+            Stopped::<u8>::Signal(TermSignal::Interrupt).to_exit_code(),
+            ExitCode::from(130)
+        );
+        assert_eq!(err.to_exit_code(), ExitCode::FAILURE);
+    }
+
+    #[test]
+    fn hands_value_over() {
+        let mut out = None;
+        let code = exit_with(Ok(true), |v| out = Some(v));
+
+        assert_eq!(out, Some(true));
+        assert_eq!(code, ExitCode::SUCCESS);
+    }
+}
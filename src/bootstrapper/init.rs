@@ -1,21 +1,28 @@
-use crossterm::event::Event;
+use crossterm::event::{Event, KeyEventKind};
 use leptos_reactive::*;
 use std::{
     error::Error,
+    io,
     sync::{
         mpsc::{self, channel},
         Arc, Mutex,
     },
+    time::{Duration, Instant},
 };
 use tui::{backend::Backend, Terminal};
 
 use super::{
     prefix_sum_2d::PrefixSum2d,
     shared_ctx::{self, Console},
+    sync_output::SyncBackend,
+    AppConfig, CrashLog, Screen,
 };
 
 use super::shared_ctx::*;
 
+// The longest tick while the terminal is unfocused
+const UNFOCUSED_POLL: Duration = Duration::from_millis(250);
+
 pub type Term<B> = Arc<Mutex<(Terminal<B>, PrefixSum2d)>>;
 
 pub trait Init<B: Backend>: Sized {
@@ -27,38 +34,162 @@ pub trait Init<B: Backend>: Sized {
     ) -> Result<Self, Box<dyn Error>>;
 }
 
-#[derive(Default)]
-pub struct AppInit(pub(super) Option<String>);
+/// Runs the app until [`Quit<T>`] is called, keeping the result
+pub struct AppInit<T = String>(pub(super) Option<Stopped<T>>, AppConfig, CrashLog);
+
+impl<T> Default for AppInit<T> {
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl<T> AppInit<T> {
+    pub fn new(config: AppConfig) -> Self {
+        Self(None, config, Default::default())
+    }
+
+    /// The history written into crash reports
+    pub fn crash_log(&self) -> CrashLog {
+        self.2.clone()
+    }
+}
 
-impl<B: Backend + 'static> Init<B> for AppInit {
+impl<B: SyncBackend + 'static, T: 'static> Init<B> for AppInit<T> {
     fn init(
         self,
         cx: Scope,
         terminal: Term<B>,
         boot: impl FnOnce(Scope) + 'static,
     ) -> Result<Self, Box<dyn Error>> {
-        let quit = Quit::attach(cx);
+        let quit = Quit::<T>::attach(cx);
         let eq = EventQueue::attach(cx);
-        let region = Region::attach(cx, terminal.try_lock().unwrap().0.size()?);
+        let region = Region::attach(cx, terminal.try_lock().unwrap().1.area());
         let rc = RenderCounter::attach(cx);
+        let shutdown = Shutdown::attach(cx);
+        let signals = TermSignals::attach(cx)?;
+        let focused = TerminalFocused::attach(cx);
+
+        // Lets the app and replays inject events next to the terminal's
+        let (tx, events) = channel();
+        EventDispatcher::attach(cx, tx);
 
         RenderBase::attach(cx, terminal);
+        use_context::<RenderBase<B>>(cx)
+            .unwrap()
+            .synchronize(self.1.sync_output);
 
-        Console::attach(cx).render_encapsulate(cx, |cx| boot(cx));
+        let console = Console::attach(cx);
+        self.2.attach(cx);
+        FocusManager::attach(cx, self.1.focus_ring);
+        EventHandlers::attach::<B>(cx);
+        DragDrop::attach::<B>(cx);
+        Clock::attach(cx, Clock::System(Instant::now()));
+        Gestures::attach(cx, self.1.gestures);
+        Keymap::attach::<B>(cx, self.1.keymap);
+        UserConfig::attach(cx, self.1.user_config.clone());
 
-        let mut count = 0;
+        #[cfg(feature = "record")]
+        if let Some(path) = &self.1.record {
+            if let Err(e) = record(cx, path) {
+                console.log(format!("Failed to record to {}: {}", path.display(), e));
+            }
+        }
+        #[cfg(feature = "record")]
+        if let Some((path, speed)) = &self.1.replay {
+            match Session::load(path) {
+                Ok(session) => {
+                    replay(cx, session, *speed);
+                }
+                Err(e) => console.log(format!("Failed to replay {}: {}", path.display(), e)),
+            }
+        }
+
+        let rb = use_context::<RenderBase<B>>(cx).unwrap();
+        let (screen, modes) = (self.1.screen, self.1.modes);
+        let suspend = Suspend::attach(
+            cx,
+            move || modes.restore(&mut io::stdout(), screen, region.get_untracked()),
+            move || {
+                modes.enter(&mut io::stdout(), screen)?;
+                rb.repaint();
+                Ok(())
+            },
+        )?;
 
-        while !quit.should_quit() {
-            let rb = use_context::<RenderBase<B>>(cx).unwrap();
-            rb.do_frame()?;
+        if let Some(key) = self.1.suspend {
+            let suspend = suspend.clone();
+            create_effect(cx, move |_| {
+                let hit = eq.with(|e| match e {
+                    Event::Key(k) => {
+                        k.code == key.code
+                            && k.modifiers == key.modifiers
+                            && k.kind == KeyEventKind::Press
+                    }
+                    _ => false,
+                });
 
-            EventQueue::poll(&eq, &region, rb)?;
-            count += 1;
+                if hit {
+                    if let Err(e) = suspend.suspend() {
+                        console.log(format!("Failed to suspend: {}", e));
+                    }
+                }
+            });
+        }
+
+        match self.1.console {
+            Some(toggle) => console.render_encapsulate(cx, toggle, boot),
+            None => boot(cx),
+        }
 
-            rc.set(count);
+        if let Some(min) = self.1.min_size {
+            min_size_guard::<B>(cx, min);
         }
 
-        Ok(Self(Some(quit.get_msg())))
+        let mut count = 0;
+
+        // Errors still run the shutdown hooks before they are returned
+        let res = (|| -> Result<(), Box<dyn Error>> {
+            // A quit held by a before-quit handler keeps the loop running
+            while !quit.should_quit() {
+                // Signals cannot be vetoed, the app only gets to clean up
+                if let Some(signal) = signals.poll() {
+                    quit.any().signal(signal);
+                    break;
+                }
+                if let Err(e) = suspend.poll() {
+                    console.log(format!("Failed to resume: {}", e));
+                }
+
+                let rb = use_context::<RenderBase<B>>(cx).unwrap();
+                rb.do_frame()?;
+
+                // Ticks slower in the background, so timers still run
+                let tick_rate = match focused.get_untracked() {
+                    true => self.1.tick_rate,
+                    false => std::cmp::max(self.1.tick_rate, UNFOCUSED_POLL),
+                };
+
+                EventQueue::poll(
+                    &eq,
+                    &events,
+                    &region,
+                    rb,
+                    self.1.screen,
+                    tick_rate,
+                    self.1.resize_debounce,
+                )?;
+
+                count += 1;
+                rc.set(count);
+            }
+
+            Ok(())
+        })();
+
+        shutdown.run();
+        res?;
+
+        Ok(Self(quit.take(), self.1, self.2))
     }
 }
 
@@ -72,39 +203,55 @@ impl TestInit {
     }
 }
 
-impl<B: Backend + 'static> Init<B> for TestInit {
+impl<B: SyncBackend + 'static> Init<B> for TestInit {
     fn init(
         self,
         cx: Scope,
         terminal: Term<B>,
         boot: impl FnOnce(Scope) + 'static,
     ) -> Result<Self, Box<dyn Error>> {
-        let quit = Quit::attach(cx);
+        let quit = Quit::<String>::attach(cx);
         let eq = EventQueue::attach(cx);
-        let region = Region::attach(cx, terminal.try_lock().unwrap().0.size()?);
+        let region = Region::attach(cx, terminal.try_lock().unwrap().1.area());
         let rc = RenderCounter::attach(cx);
+        let shutdown = Shutdown::attach(cx);
+        TerminalFocused::attach(cx);
 
         EventDispatcher::attach(cx, self.1.clone());
         RenderBase::attach(cx, Arc::clone(&terminal));
+        FocusManager::attach(cx, Default::default());
+        EventHandlers::attach::<B>(cx);
+        DragDrop::attach::<B>(cx);
+        Clock::attach(cx, Clock::Virtual(Default::default()));
+        Gestures::attach(cx, Default::default());
+        Keymap::attach::<B>(cx, Default::default());
 
         let mut count = 0;
 
         boot(cx);
 
-        while !quit.should_quit()
-            && match self.0 {
-                Some(v) => count < v,
-                _ => true,
+        // Errors still run the shutdown hooks before they are returned
+        let res = (|| -> Result<(), Box<dyn Error>> {
+            while !quit.should_quit()
+                && match self.0 {
+                    Some(v) => count < v,
+                    _ => true,
+                }
+            {
+                let rb = use_context::<RenderBase<B>>(cx).unwrap();
+                rb.do_frame()?;
+
+                EventQueue::test_poll(&eq, &self.2, &region, rb, Screen::Alternate)?;
+                count += 1;
+
+                rc.set(count);
             }
-        {
-            let rb = use_context::<RenderBase<B>>(cx).unwrap();
-            rb.do_frame()?;
 
-            EventQueue::test_poll(&eq, &self.2, &region, rb)?;
-            count += 1;
+            Ok(())
+        })();
 
-            rc.set(count);
-        }
+        shutdown.run();
+        res?;
 
         Ok(self)
     }
@@ -0,0 +1,329 @@
+use std::{cell::RefCell, collections::HashMap, panic::Location, rc::Rc};
+
+use leptos_reactive::{RwSignal, ScopeId};
+use tui::layout::Rect;
+
+use super::shared_ctx::Layer;
+
+/// Where a scope renders from. A scope may render from several effects that
+/// re-run independently, so each site only replaces its own areas.
+pub(crate) type Site = &'static Location<'static>;
+
+#[derive(Default)]
+struct Drawn {
+    areas: Vec<(Rect, Layer)>,
+    // The render pass the areas were recorded in
+    pass: usize,
+}
+
+pub(crate) struct Mount {
+    // Mount order, parents are generally mounted before their children
+    seq: usize,
+    // The scope followed by its ancestors, kept as the scopes may be gone
+    // by the time it is needed
+    ancestry: Vec<ScopeId>,
+    sites: HashMap<Site, Drawn>,
+
+    redraw: RwSignal<()>,
+}
+
+impl Mount {
+    fn areas(&self) -> impl Iterator<Item = &(Rect, Layer)> {
+        self.sites.values().flat_map(|d| &d.areas)
+    }
+}
+
+/// A mounted area under the pointer. `area` and `point` share a coordinate
+/// space, which for mounts inside an offscreen buffer is the buffer's.
+pub(crate) struct Hit {
+    pub ancestry: Vec<ScopeId>,
+    pub area: Rect,
+    pub point: (u16, u16),
+}
+
+// The mounts of an offscreen buffer, hit through where its owner shows it
+struct Nested {
+    mounts: Rc<RefCell<Mounts>>,
+    // Where the buffer is shown, before clipping
+    view: Rect,
+    // The position within the buffer shown at the top left of `view`
+    offset: (u16, u16),
+}
+
+#[derive(Default)]
+pub(crate) struct Mounts {
+    seq: usize,
+    scopes: HashMap<ScopeId, Mount>,
+    nested: HashMap<ScopeId, Nested>,
+
+    unmounted: Vec<Rect>,
+}
+
+impl Mounts {
+    /// Records that `id` rendered into `area` from `site` during `pass`,
+    /// returns `None` if the scope has not been mounted yet. The first render
+    /// of a later pass from the same site replaces its areas, so a component
+    /// that moved no longer owns where it used to be.
+    pub fn track(
+        &mut self,
+        id: ScopeId,
+        site: Site,
+        area: Rect,
+        layer: Layer,
+        pass: usize,
+    ) -> Option<RwSignal<()>> {
+        let mount = self.scopes.get_mut(&id)?;
+        let drawn = mount.sites.entry(site).or_default();
+
+        if drawn.pass != pass {
+            drawn.pass = pass;
+            drawn.areas.clear();
+        }
+        if !drawn.areas.contains(&(area, layer)) {
+            drawn.areas.push((area, layer));
+        }
+
+        Some(mount.redraw)
+    }
+
+    /// Records that `id` rendered nothing visible from `site` during `pass`,
+    /// so the site no longer owns the areas of an earlier pass
+    pub fn skip(&mut self, id: ScopeId, site: Site, pass: usize) {
+        let drawn = self
+            .scopes
+            .get_mut(&id)
+            .and_then(|m| m.sites.get_mut(site))
+            .filter(|d| d.pass != pass);
+
+        if let Some(drawn) = drawn {
+            drawn.pass = pass;
+            drawn.areas.clear();
+        }
+    }
+
+    /// Lets the pointer reach `mounts` through the buffer `owner` shows in
+    /// `view`, starting at `offset` within it
+    pub fn nest(
+        &mut self,
+        owner: ScopeId,
+        mounts: Rc<RefCell<Mounts>>,
+        view: Rect,
+        offset: (u16, u16),
+    ) {
+        self.nested.insert(
+            owner,
+            Nested {
+                mounts,
+                view,
+                offset,
+            },
+        );
+    }
+
+    pub fn unnest(&mut self, owner: ScopeId) {
+        self.nested.remove(&owner);
+    }
+
+    pub fn mount(
+        &mut self,
+        ancestry: Vec<ScopeId>,
+        site: Site,
+        area: Rect,
+        layer: Layer,
+        pass: usize,
+        redraw: RwSignal<()>,
+    ) {
+        self.seq += 1;
+
+        let drawn = Drawn {
+            areas: vec![(area, layer)],
+            pass,
+        };
+        self.scopes.insert(
+            ancestry[0],
+            Mount {
+                seq: self.seq,
+                ancestry,
+                sites: HashMap::from([(site, drawn)]),
+                redraw,
+            },
+        );
+    }
+
+    pub fn unmount(&mut self, id: ScopeId) {
+        if let Some(mount) = self.scopes.remove(&id) {
+            self.unmounted.extend(mount.areas().map(|(a, _)| *a));
+        }
+    }
+
+    pub fn take_unmounted(&mut self) -> Vec<Rect> {
+        std::mem::take(&mut self.unmounted)
+    }
+
+    /// Finds every mount that has to be redrawn once `damage` is cleared.
+    /// Redrawing a mount damages its own areas, so anything mounted after it
+    /// that overlaps is redrawn as well.
+    pub fn damaged(&self, damage: &[Rect]) -> Vec<RwSignal<()>> {
+        let mut damage = damage.to_vec();
+
+        self.ordered()
+            .into_iter()
+            .filter_map(|mount| {
+                let hit = mount
+                    .areas()
+                    .any(|(a, _)| damage.iter().any(|d| a.intersects(*d)));
+
+                if hit {
+                    damage.extend(mount.areas().map(|(a, _)| *a));
+                }
+
+                hit.then_some(mount.redraw)
+            })
+            .collect()
+    }
+
+    pub fn all(&self) -> Vec<RwSignal<()>> {
+        self.ordered().into_iter().map(|v| v.redraw).collect()
+    }
+
+    /// Every mount with an area containing `(x, y)`, the topmost first.
+    /// Higher layers are on top, then whatever was mounted last. Mounts
+    /// within an offscreen buffer come right before the mount showing it.
+    pub fn hit(&self, x: u16, y: u16) -> Vec<Hit> {
+        let contains = |a: &Rect| a.left() <= x && x < a.right() && a.top() <= y && y < a.bottom();
+
+        let mut hits = self
+            .scopes
+            .values()
+            .filter_map(|mount| {
+                let (area, layer) = mount
+                    .areas()
+                    .filter(|(a, _)| contains(a))
+                    .max_by_key(|(_, l)| *l)?;
+
+                Some(((*layer, mount.seq), mount, *area))
+            })
+            .collect::<Vec<_>>();
+        hits.sort_by_key(|(order, ..)| std::cmp::Reverse(*order));
+
+        hits.into_iter()
+            .flat_map(|(_, mount, area)| {
+                let nested = self
+                    .nested
+                    .get(&mount.ancestry[0])
+                    .filter(|n| contains(&n.view))
+                    .map(|n| {
+                        let (bx, by) = (x - n.view.x, y - n.view.y);
+                        match (bx.checked_add(n.offset.0), by.checked_add(n.offset.1)) {
+                            (Some(bx), Some(by)) => n.mounts.borrow().hit(bx, by),
+                            _ => vec![],
+                        }
+                    })
+                    .unwrap_or_default();
+
+                nested.into_iter().chain(std::iter::once(Hit {
+                    ancestry: mount.ancestry.clone(),
+                    area,
+                    point: (x, y),
+                }))
+            })
+            .collect()
+    }
+
+    fn ordered(&self) -> Vec<&Mount> {
+        let mut mounts = self.scopes.values().collect::<Vec<_>>();
+        mounts.sort_by_key(|v| v.seq);
+
+        mounts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, panic::Location, rc::Rc};
+
+    use leptos_reactive::*;
+    use tui::layout::Rect;
+
+    use super::Mounts;
+
+    #[test]
+    fn later_passes_replace_areas() {
+        create_scope(create_runtime(), |cx| {
+            let mut mounts = Mounts::default();
+            let (a, b, c) = (
+                Rect::new(0, 0, 2, 2),
+                Rect::new(4, 0, 2, 2),
+                Rect::new(8, 0, 2, 2),
+            );
+
+            let (site, other) = (Location::caller(), Location::caller());
+
+            mounts.mount(cx.ancestry(), site, a, 0, 0, create_rw_signal(cx, ()));
+            mounts.track(cx.id(), site, b, 0, 0);
+            mounts.track(cx.id(), site, c, 0, 1);
+
+            assert!(mounts.hit(0, 0).is_empty());
+            assert_eq!(mounts.hit(8, 0).len(), 1);
+
+            // Another site of the scope leaves the areas alone
+            mounts.track(cx.id(), other, a, 0, 2);
+            assert_eq!(mounts.hit(8, 0).len(), 1);
+            assert_eq!(mounts.hit(0, 0).len(), 1);
+
+            mounts.skip(cx.id(), site, 2);
+            assert!(mounts.hit(8, 0).is_empty());
+            assert_eq!(mounts.hit(0, 0).len(), 1);
+
+            mounts.unmount(cx.id());
+            assert_eq!(mounts.take_unmounted(), [a]);
+        })
+        .dispose();
+    }
+
+    #[test]
+    fn hits_reach_into_nested_mounts() {
+        create_scope(create_runtime(), |cx| {
+            let inner = Rc::new(RefCell::new(Mounts::default()));
+            let mut mounts = Mounts::default();
+
+            cx.child_scope(|child| {
+                // Scrolled by 3 columns and cut off at the left by a clip
+                let view = Rect::new(2, 1, 6, 2);
+                mounts.mount(
+                    cx.ancestry(),
+                    Location::caller(),
+                    Rect::new(4, 1, 4, 2),
+                    0,
+                    0,
+                    create_rw_signal(cx, ()),
+                );
+                mounts.nest(cx.id(), Rc::clone(&inner), view, (3, 0));
+
+                let rw = create_rw_signal(child, ());
+                inner.borrow_mut().mount(
+                    child.ancestry(),
+                    Location::caller(),
+                    Rect::new(5, 0, 2, 1),
+                    0,
+                    0,
+                    rw,
+                );
+
+                let hits = mounts.hit(5, 1);
+                assert_eq!(hits.len(), 2);
+                assert_eq!(hits[0].ancestry[0], child.id());
+                assert_eq!(hits[0].point, (6, 0));
+                assert_eq!(hits[0].area, Rect::new(5, 0, 2, 1));
+                assert_eq!(hits[1].ancestry[0], cx.id());
+
+                // Within the buffer but clipped away
+                assert!(mounts.hit(3, 1).is_empty());
+
+                mounts.unnest(cx.id());
+                assert_eq!(mounts.hit(5, 1).len(), 1);
+            });
+        })
+        .dispose();
+    }
+}
@@ -68,7 +68,7 @@ impl<'a> Iterator for PrefixSum2dIterator<'a> {
 
             self.idx += 1;
 
-            Some((x, y, self.columns[xu]))
+            Some((x + self.parent.sz.x, y + self.parent.sz.y, self.columns[xu]))
         } else {
             None
         }
@@ -86,7 +86,24 @@ impl PrefixSum2d {
         }
     }
 
+    pub fn area(&self) -> Rect {
+        self.sz
+    }
+
     pub fn insert_mul(&mut self, bound: Rect, mul: isize) {
+        if !bound.intersects(self.sz) {
+            return;
+        }
+
+        // Relative to the origin of the area, which is not at zero when inline
+        let bound = bound.intersection(self.sz);
+        let bound = Rect::new(
+            bound.x - self.sz.x,
+            bound.y - self.sz.y,
+            bound.width,
+            bound.height,
+        );
+
         match self.inner_bound {
             Some(mut v) => {
                 v.0 = std::cmp::min(v.0, bound.x);
@@ -138,7 +155,6 @@ impl PrefixSum2d {
         }
 
         if bound.x + bound.width < self.sz.width {
-            ca &= true;
             let i = bound.y * self.sz.width + bound.x + bound.width;
             let i = i as usize;
 
@@ -147,6 +163,8 @@ impl PrefixSum2d {
             }
 
             self.ir[i] -= mul;
+        } else {
+            ca = false;
         }
         if ca {
             let i = (bound.y + bound.height) * self.sz.width + bound.x + bound.width;
@@ -202,6 +220,7 @@ impl PrefixSum2d {
     pub fn resize(&mut self, sz: Rect) {
         self.clear();
 
+        self.sz = sz;
         self.ir.resize(sz.height as usize * sz.width as usize, 0);
     }
 }
@@ -281,4 +300,31 @@ mod tests {
 
         assert_eq!(iterator, vec![1, 0, 0, 0, 0, 0, 0, 0, 0])
     }
+
+    #[test]
+    fn offset_origin() {
+        let sz = Rect::new(0, 5, 2, 2);
+
+        let mut base = PrefixSum2d::new(sz);
+        base.insert(Rect::new(1, 6, 4, 4));
+
+        let iterator = base.iter().collect::<Vec<_>>();
+
+        assert_eq!(iterator, vec![(0, 5, 0), (1, 5, 0), (0, 6, 0), (1, 6, 1)])
+    }
+
+    #[test]
+    fn resized() {
+        let mut base = PrefixSum2d::new(Rect::new(0, 0, 2, 2));
+        base.insert(Rect::new(0, 0, 2, 2));
+
+        base.resize(Rect::new(0, 0, 3, 1));
+        base.insert(Rect::new(1, 0, 2, 1));
+
+        assert_eq!(base.area(), Rect::new(0, 0, 3, 1));
+        assert_eq!(
+            base.iter().collect::<Vec<_>>(),
+            vec![(0, 0, 0), (1, 0, 1), (2, 0, 1)]
+        );
+    }
 }
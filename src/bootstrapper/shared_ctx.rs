@@ -1,13 +1,42 @@
+mod config;
 mod console;
+mod drag;
 mod eq;
+mod focus;
+mod gesture;
+mod handlers;
+mod hover;
+mod keymap;
+mod min_size;
+mod offscreen;
 mod quit;
 mod rb;
+#[cfg(feature = "record")]
+mod record;
 mod region;
+mod shutdown;
+mod signals;
+mod suspend;
+mod term_focus;
 mod test;
 
+pub use config::*;
 pub use console::*;
+pub use drag::*;
 pub use eq::*;
+pub use focus::*;
+pub use gesture::*;
+pub use handlers::*;
+pub use hover::*;
+pub use keymap::*;
+pub use min_size::*;
 pub use quit::*;
 pub use rb::*;
+#[cfg(feature = "record")]
+pub use record::*;
 pub use region::*;
+pub use shutdown::*;
+pub use signals::*;
+pub use suspend::*;
+pub use term_focus::*;
 pub use test::*;
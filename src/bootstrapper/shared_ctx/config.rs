@@ -0,0 +1,428 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::{Duration, SystemTime},
+};
+
+use leptos_reactive::*;
+use toml::Value;
+use tui::style::{Color, Modifier, Style};
+
+use super::{bind, Clock, Console, RenderCounter};
+
+// Checking the file is a syscall, so it is not done on every tick
+const RELOAD_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Settings read from a TOML file, like
+///
+/// ```toml
+/// [keys]
+/// save = "Ctrl-x Ctrl-s"
+/// find = ["<leader>f", "Ctrl-f"]
+///
+/// [styles]
+/// error = "red"
+/// title = { fg = "#ffaf00", bg = "black", modifiers = ["bold", "underlined"] }
+/// ```
+///
+/// The file is reloaded once it changes. Errors are logged on the
+/// [`Console`] and leave the previous values in place.
+#[derive(Clone, Copy)]
+pub struct UserConfig {
+    keys: RwSignal<HashMap<String, Vec<String>>>,
+    styles: RwSignal<HashMap<String, Style>>,
+
+    path: StoredValue<Option<PathBuf>>,
+    // When the file was last modified and when that was checked
+    modified: StoredValue<(Option<SystemTime>, Duration)>,
+}
+
+impl UserConfig {
+    pub fn attach(cx: Scope, path: Option<PathBuf>) -> Self {
+        let v = Self {
+            keys: create_rw_signal(cx, Default::default()),
+            styles: create_rw_signal(cx, Default::default()),
+            path: store_value(cx, path),
+            modified: store_value(cx, Default::default()),
+        };
+        provide_context(cx, v);
+
+        let console = use_context::<Console>(cx);
+        let log = move |e: String| match console {
+            Some(console) => console.log(e),
+            None => eprintln!("{}", e),
+        };
+
+        v.reload().unwrap_or_else(log);
+
+        let rc: RenderCounter = use_context(cx).unwrap();
+        let clock = use_context::<Clock>(cx).unwrap();
+        create_effect(cx, move |_| {
+            rc.0.get();
+
+            let now = clock.now();
+            let (_, checked) = v.modified.get_value();
+            if now.saturating_sub(checked) < RELOAD_INTERVAL {
+                return;
+            }
+            v.modified.update_value(|m| m.1 = now);
+
+            cx.untrack(|| v.reload()).unwrap_or_else(log);
+        });
+
+        v
+    }
+
+    /// `config.toml` in the directory for `app` under `$XDG_CONFIG_HOME`,
+    /// or `~/.config` when it is not set
+    pub fn xdg_path(app: &str) -> Option<PathBuf> {
+        let base = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|v| v.is_absolute())
+            .or_else(|| std::env::var_os("HOME").map(|v| Path::new(&v).join(".config")))?;
+
+        Some(base.join(app).join("config.toml"))
+    }
+
+    /// The key sequences configured for `action`
+    pub fn keys(&self, action: &str) -> Option<Vec<String>> {
+        self.keys.with(|v| v.get(action).cloned())
+    }
+
+    /// The style configured as `name`
+    pub fn style(&self, name: &str) -> Option<Style> {
+        self.styles.with(|v| v.get(name).copied())
+    }
+
+    /// Reads the file again if it has changed since it was last read
+    fn reload(&self) -> Result<(), String> {
+        let Some(path) = self.path.get_value() else {
+            return Ok(());
+        };
+
+        let modified = match fs::metadata(&path).and_then(|m| m.modified()) {
+            Ok(v) => Some(v),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        };
+
+        if self.modified.with_value(|m| m.0) == modified {
+            return Ok(());
+        }
+        self.modified.update_value(|m| m.0 = modified);
+
+        // A removed file goes back to the defaults
+        let text = match modified {
+            Some(_) => fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?,
+            None => String::new(),
+        };
+
+        let (keys, styles) = parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        if self.keys.with_untracked(|v| *v != keys) {
+            self.keys.set(keys);
+        }
+        if self.styles.with_untracked(|v| *v != styles) {
+            self.styles.set(styles);
+        }
+
+        Ok(())
+    }
+}
+
+type Parsed = (HashMap<String, Vec<String>>, HashMap<String, Style>);
+
+fn parse(text: &str) -> Result<Parsed, String> {
+    let value = text.parse::<Value>().map_err(|e| e.to_string())?;
+
+    let table = |name| match value.get(name) {
+        Some(Value::Table(t)) => Ok(t.iter().collect::<Vec<_>>()),
+        Some(_) => Err(format!("`{}` has to be a table", name)),
+        None => Ok(vec![]),
+    };
+
+    let keys = table("keys")?
+        .into_iter()
+        .map(|(action, seqs)| {
+            let seqs = match seqs {
+                Value::String(s) => vec![s.clone()],
+                Value::Array(a) => a
+                    .iter()
+                    .map(|v| v.as_str().map(String::from))
+                    .collect::<Option<_>>()
+                    .ok_or_else(|| format!("keys.{} has to be a list of strings", action))?,
+                _ => return Err(format!("keys.{} has to be a key sequence", action)),
+            };
+
+            Ok((action.clone(), seqs))
+        })
+        .collect::<Result<_, String>>()?;
+
+    let styles = table("styles")?
+        .into_iter()
+        .map(|(name, v)| {
+            let style = parse_style(v).map_err(|e| format!("styles.{}: {}", name, e))?;
+            Ok((name.clone(), style))
+        })
+        .collect::<Result<_, String>>()?;
+
+    Ok((keys, styles))
+}
+
+/// A style is either a foreground colour or a table of `fg`, `bg` and
+/// `modifiers`
+fn parse_style(v: &Value) -> Result<Style, String> {
+    let table = match v {
+        Value::String(fg) => return Ok(Style::default().fg(parse_color(fg)?)),
+        Value::Table(t) => t,
+        _ => return Err("expected a colour or a table".into()),
+    };
+
+    let mut style = Style::default();
+
+    for (k, v) in table {
+        let str = || {
+            v.as_str()
+                .ok_or_else(|| format!("`{}` has to be a string", k))
+        };
+
+        style = match k.as_str() {
+            "fg" => style.fg(parse_color(str()?)?),
+            "bg" => style.bg(parse_color(str()?)?),
+            "modifiers" => {
+                let list = v.as_array().ok_or("`modifiers` has to be a list")?;
+
+                list.iter().try_fold(style, |style, m| {
+                    let m = m
+                        .as_str()
+                        .ok_or("`modifiers` has to be a list of strings")?;
+                    Ok::<_, String>(style.add_modifier(parse_modifier(m)?))
+                })?
+            }
+            _ => return Err(format!("unknown field `{}`", k)),
+        };
+    }
+
+    Ok(style)
+}
+
+fn parse_color(s: &str) -> Result<Color, String> {
+    if let Some(hex) = s.strip_prefix('#') {
+        let rgb = u32::from_str_radix(hex, 16)
+            .ok()
+            .filter(|_| hex.len() == 6)
+            .ok_or_else(|| format!("invalid colour `{}`", s))?;
+
+        return Ok(Color::Rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8));
+    }
+    if let Ok(i) = s.parse() {
+        return Ok(Color::Indexed(i));
+    }
+
+    Ok(
+        match s.to_lowercase().replace(['_', '-', ' '], "").as_str() {
+            "reset" => Color::Reset,
+            "black" => Color::Black,
+            "red" => Color::Red,
+            "green" => Color::Green,
+            "yellow" => Color::Yellow,
+            "blue" => Color::Blue,
+            "magenta" => Color::Magenta,
+            "cyan" => Color::Cyan,
+            "gray" | "grey" => Color::Gray,
+            "darkgray" | "darkgrey" => Color::DarkGray,
+            "lightred" => Color::LightRed,
+            "lightgreen" => Color::LightGreen,
+            "lightyellow" => Color::LightYellow,
+            "lightblue" => Color::LightBlue,
+            "lightmagenta" => Color::LightMagenta,
+            "lightcyan" => Color::LightCyan,
+            "white" => Color::White,
+            _ => return Err(format!("unknown colour `{}`", s)),
+        },
+    )
+}
+
+fn parse_modifier(s: &str) -> Result<Modifier, String> {
+    Ok(
+        match s.to_lowercase().replace(['_', '-', ' '], "").as_str() {
+            "bold" => Modifier::BOLD,
+            "dim" => Modifier::DIM,
+            "italic" => Modifier::ITALIC,
+            "underlined" | "underline" => Modifier::UNDERLINED,
+            "slowblink" | "blink" => Modifier::SLOW_BLINK,
+            "rapidblink" => Modifier::RAPID_BLINK,
+            "reversed" | "reverse" => Modifier::REVERSED,
+            "hidden" => Modifier::HIDDEN,
+            "crossedout" | "strikethrough" => Modifier::CROSSED_OUT,
+            _ => return Err(format!("unknown modifier `{}`", s)),
+        },
+    )
+}
+
+/// The style configured as `name`, or `default` without one
+pub fn use_style(cx: Scope, name: &str, default: Style) -> Memo<Style> {
+    let user = use_context::<UserConfig>(cx);
+    let name = name.to_string();
+
+    create_memo(cx, move |_| {
+        user.and_then(|u| u.style(&name)).unwrap_or(default)
+    })
+}
+
+/// Like [`bind`], with the sequences of `action` taken from the
+/// [`UserConfig`] and `defaults` used when it has none. The action is bound
+/// again whenever the configuration changes.
+pub fn bind_action(
+    cx: Scope,
+    mode: &str,
+    action: &str,
+    defaults: &[&str],
+    description: impl Into<String>,
+    f: impl Fn() + 'static,
+) {
+    let user = use_context::<UserConfig>(cx);
+    let console = use_context::<Console>(cx);
+
+    let (mode, action, description) = (mode.to_string(), action.to_string(), description.into());
+    let defaults = defaults.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+    let f: Rc<dyn Fn()> = Rc::new(f);
+
+    create_effect(cx, move |prev: Option<ScopeDisposer>| {
+        let seqs = user
+            .and_then(|u| u.keys(&action))
+            .unwrap_or_else(|| defaults.clone());
+
+        if let Some(prev) = prev {
+            prev.dispose();
+        }
+
+        cx.child_scope(|cx| {
+            cx.untrack(|| {
+                for seq in seqs {
+                    let f = Rc::clone(&f);
+
+                    if let Err(e) = bind(cx, &mode, &seq, description.clone(), move || f()) {
+                        let e = format!("keys.{}: {}", action, e);
+                        match console {
+                            Some(console) => console.log(e),
+                            None => eprintln!("{}", e),
+                        }
+                    }
+                }
+            })
+        })
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::RefCell,
+        fs::{self, File},
+        rc::Rc,
+        time::{Duration, SystemTime},
+    };
+
+    use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+    use leptos_reactive::*;
+    use tui::style::{Color, Modifier, Style};
+
+    use crate::bootstrapper::{
+        shared_ctx::{Clock, Console, EventDispatcher, RenderCounter},
+        test_bootstrap,
+    };
+
+    use super::{bind_action, use_style, UserConfig};
+
+    #[test]
+    fn reloads_keys_and_styles() {
+        let path = std::env::temp_dir().join(format!("x-tui-config-{}.toml", std::process::id()));
+
+        // Set explicitly, as rewrites can land within the same timestamp
+        let write = {
+            let path = path.clone();
+            move |text: &str, age: u64| {
+                fs::write(&path, text).unwrap();
+                File::options()
+                    .write(true)
+                    .open(&path)
+                    .unwrap()
+                    .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(age))
+                    .unwrap();
+            }
+        };
+        write("[keys]\nsave = \"Ctrl-s\"\n[styles]\ntitle = \"red\"\n", 1);
+
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let inner = Rc::clone(&log);
+        let file = path.clone();
+
+        test_bootstrap(
+            move |cx| {
+                let console = Console::attach(cx);
+                UserConfig::attach(cx, Some(file));
+
+                let ed = use_context::<EventDispatcher>(cx).unwrap();
+                let rc = use_context::<RenderCounter>(cx).unwrap();
+                let clock = use_context::<Clock>(cx).unwrap();
+
+                for (action, default) in [("save", "Ctrl-x Ctrl-s"), ("quit", "q")] {
+                    let log = Rc::clone(&inner);
+                    bind_action(cx, "normal", action, &[default], action, move || {
+                        log.borrow_mut().push(action)
+                    });
+                }
+                let title = use_style(cx, "title", Style::default());
+                assert_eq!(title.get_untracked(), Style::default().fg(Color::Red));
+
+                let ctrl = |c| Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL));
+                let q = Event::Key(KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE));
+
+                create_effect(cx, move |_| match rc.0.get() {
+                    0 => {
+                        ed.dispatch(ctrl('s')).unwrap();
+                        ed.dispatch(q.clone()).unwrap();
+                    }
+                    2 => {
+                        write(
+                            "[keys]\nsave = [\"Ctrl-w\"]\n[styles.title]\nfg = \"blue\"\nmodifiers = [\"bold\"]\n",
+                            2,
+                        );
+                        clock.advance(Duration::from_secs(1));
+                    }
+                    3 => {
+                        let bold = Style::default().fg(Color::Blue).add_modifier(Modifier::BOLD);
+                        assert_eq!(title.get_untracked(), bold);
+
+                        ed.dispatch(ctrl('s')).unwrap();
+                        ed.dispatch(ctrl('w')).unwrap();
+                    }
+                    5 => {
+                        write("[keys\n", 3);
+                        clock.advance(Duration::from_secs(1));
+                    }
+                    6 => {
+                        let error = console.with_untracked(|v| v.last().unwrap().1.clone());
+                        assert!(error.contains("x-tui-config"), "{}", error);
+
+                        // The last good file still applies
+                        ed.dispatch(ctrl('w')).unwrap();
+                    }
+                    _ => (),
+                });
+            },
+            10,
+            10,
+            Some(8),
+        )
+        .unwrap();
+
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(*log.borrow(), ["save", "quit", "save", "save"]);
+    }
+}
@@ -84,14 +84,18 @@ impl Console {
         v
     }
 
-    pub fn render_encapsulate(self, cx: Scope, child: impl FnOnce(Scope)) {
+    pub fn render_encapsulate(self, cx: Scope, toggle: KeyCode, child: impl FnOnce(Scope)) {
         let region: Region = use_context(cx).unwrap();
         let eq: EventQueue = use_context(cx).unwrap();
 
         let when = create_memo(cx, move |v| {
             let v = v.cloned().unwrap_or(false);
             eq.0.with(move |e| match e {
-                crossterm::event::Event::Key(k) if k.code == KeyCode::F(12) => !v,
+                crossterm::event::Event::Key(k)
+                    if k.code == toggle && k.kind == crossterm::event::KeyEventKind::Press =>
+                {
+                    !v
+                }
                 _ => v,
             })
         });
@@ -121,6 +125,8 @@ impl Console {
             cx,
             when,
             move |cx| {
+                Region::derive(cx, my_area);
+
                 let block = store_value(
                     cx,
                     Block::default()
@@ -128,16 +134,20 @@ impl Console {
                         .style(tui::style::Style::default().bg(tui::style::Color::Red)),
                 );
 
+                let local_area = Signal::derive(cx, move || {
+                    let r = my_area();
+                    Rect::new(0, 0, r.width, r.height)
+                });
                 let inner_area =
-                    Signal::derive(cx, move || block.with(|block| block.inner(my_area())));
+                    Signal::derive(cx, move || block.with(|block| block.inner(local_area())));
 
-                let rb: RenderBaseAuto = use_context(cx).unwrap();
+                let rb = use_context::<RenderBaseAuto>(cx).unwrap().local(cx);
 
                 create_effect(cx, move |_| {
-                    let reg = my_area();
+                    let reg = local_area();
 
-                    rb.render(Clear, reg);
-                    rb.render(block(), reg);
+                    rb.render(cx, Clear, reg);
+                    rb.render(cx, block(), reg);
                 });
 
                 each(
@@ -191,18 +201,18 @@ impl Console {
                         ))
                     },
                     move |cx, data| {
-                        let rb: RenderBaseAuto = use_context(cx).unwrap();
+                        let rb = use_context::<RenderBaseAuto>(cx).unwrap().local(cx);
                         data.with(move |(off, h, p)| {
                             let inner_area = inner_area();
 
                             let rect = Rect::new(
-                                1,
+                                inner_area.x,
                                 inner_area.y + inner_area.height - off,
                                 inner_area.width,
                                 *h,
                             );
 
-                            rb.render(p.clone(), rect);
+                            rb.render(cx, p.clone(), rect);
                         })
                     },
                 );
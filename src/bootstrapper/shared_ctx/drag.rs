@@ -0,0 +1,483 @@
+use std::{any::Any, cell::Cell, rc::Rc};
+
+use crossterm::event::{MouseButton, MouseEventKind};
+use leptos_reactive::*;
+use tui::{backend::Backend, layout::Rect, widgets::Paragraph};
+
+use crate::controlflow::show;
+
+use super::{on_pointer, EventHandlers, Layer, Propagation, Region, RenderBase};
+
+/// A drag with the left button, positions are absolute
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Drag {
+    pub origin: (u16, u16),
+    pub position: (u16, u16),
+    /// Set once the button is released
+    pub done: bool,
+}
+
+impl Drag {
+    pub fn delta(&self) -> (i32, i32) {
+        (
+            self.position.0 as i32 - self.origin.0 as i32,
+            self.position.1 as i32 - self.origin.1 as i32,
+        )
+    }
+}
+
+/// Calls `f` as whatever `cx` rendered is dragged, starting with the press.
+/// The pointer is captured until release, so dividers and scrollbar thumbs
+/// keep following it outside of their area. A release outside of the
+/// terminal ends the drag once the pointer is back or the terminal loses
+/// focus.
+pub fn on_drag(cx: Scope, f: impl Fn(Drag) + 'static) {
+    let handlers = use_context::<EventHandlers>(cx).unwrap();
+    let f = Rc::new(f);
+
+    // Only the latest drag can still hold the capture
+    let capture = Rc::new(Cell::new(None));
+    {
+        let capture = Rc::clone(&capture);
+        on_cleanup(cx, move || {
+            if let Some(id) = capture.get() {
+                handlers.release(id)
+            }
+        });
+    }
+
+    on_pointer(cx, move |m| {
+        if m.kind != MouseEventKind::Down(MouseButton::Left) {
+            return Propagation::Continue;
+        }
+
+        let origin = handlers.pointer();
+        f(Drag {
+            origin,
+            position: origin,
+            done: false,
+        });
+
+        let f = Rc::clone(&f);
+        let id = handlers.capture_until_release(move |m| {
+            f(Drag {
+                origin,
+                position: (m.column, m.row),
+                done: matches!(m.kind, MouseEventKind::Up(_)),
+            })
+        });
+        capture.set(Some(id));
+
+        Propagation::Stop
+    });
+}
+
+#[derive(Clone)]
+struct Active {
+    payload: Rc<dyn Any>,
+    label: String,
+    position: (u16, u16),
+}
+
+type Accept = Rc<dyn Fn(&dyn Any) -> bool>;
+type OnDrop = Rc<dyn Fn(&dyn Any)>;
+
+struct Target {
+    id: usize,
+    scope: ScopeId,
+    accept: Accept,
+    drop: OnDrop,
+}
+
+#[derive(Default)]
+struct Targets {
+    next: usize,
+    list: Vec<Target>,
+}
+
+/// The drag in progress, drawn as a ghost above everything else
+#[derive(Clone, Copy)]
+pub struct DragDrop {
+    active: RwSignal<Option<Active>>,
+    targets: StoredValue<Targets>,
+    handlers: EventHandlers,
+}
+
+impl DragDrop {
+    pub fn attach<B: Backend + 'static>(cx: Scope) -> Self {
+        let v = Self {
+            active: create_rw_signal(cx, None),
+            targets: store_value(cx, Default::default()),
+            handlers: use_context(cx).unwrap(),
+        };
+        provide_context(cx, v);
+
+        show(
+            cx,
+            move || v.active.with(Option::is_some),
+            move |cx| {
+                let rb = use_context::<RenderBase<B>>(cx)
+                    .unwrap()
+                    .layer(Layer::MAX - 1);
+                let region: Region = use_context(cx).unwrap();
+
+                // Each position gets its own scope, so the last one is cleared
+                create_effect(cx, move |prev: Option<Option<ScopeDisposer>>| {
+                    if let Some(Some(prev)) = prev {
+                        prev.dispose();
+                    }
+
+                    let (label, (x, y)) = v
+                        .active
+                        .with(|a| a.as_ref().map(|a| (a.label.clone(), a.position)))?;
+
+                    // The pointer can be outside an inline viewport
+                    let r = region();
+                    let width = std::cmp::min(label.chars().count() as u16, u16::MAX - x);
+                    let area = Rect::new(x, y, width, 1);
+                    if !area.intersects(r) {
+                        return None;
+                    }
+
+                    let rb = rb.clone();
+                    Some(cx.child_scope(move |cx| {
+                        rb.render(cx, Paragraph::new(label), area.intersection(r))
+                    }))
+                });
+            },
+            |_cx| {},
+        );
+
+        v
+    }
+
+    /// Whether a drag is in progress
+    pub fn is_dragging(&self) -> bool {
+        self.active.with(Option::is_some)
+    }
+
+    fn drop_at(&self, position: (u16, u16)) {
+        let Some(active) = self.active.get_untracked() else {
+            return;
+        };
+        self.active.set(None);
+
+        let hits = self.handlers.hits(position.0, position.1);
+        let target = self.targets.with_value(|t| {
+            hits.iter().flatten().find_map(|scope| {
+                t.list
+                    .iter()
+                    .find(|v| v.scope == *scope && (v.accept)(&*active.payload))
+                    .map(|v| Rc::clone(&v.drop))
+            })
+        });
+
+        if let Some(drop) = target {
+            drop(&*active.payload)
+        }
+    }
+}
+
+/// Lets whatever `cx` rendered be dragged onto a [`drop_target`], carrying
+/// the `payload` at the time the drag starts
+pub fn draggable<P: 'static>(
+    cx: Scope,
+    label: impl Into<String>,
+    payload: impl Fn() -> P + 'static,
+) {
+    let dd = use_context::<DragDrop>(cx).unwrap();
+    let label = label.into();
+
+    on_drag(cx, move |drag| {
+        if drag.done {
+            return dd.drop_at(drag.position);
+        }
+
+        if dd.active.with_untracked(Option::is_none) {
+            dd.active.set(Some(Active {
+                payload: Rc::new(payload()),
+                label: label.clone(),
+                position: drag.position,
+            }));
+        } else {
+            dd.active
+                .update(|a| a.iter_mut().for_each(|a| a.position = drag.position));
+        }
+    });
+}
+
+/// The state of a [`drop_target`], for highlighting it
+#[derive(Clone, Copy)]
+pub struct DropTarget {
+    /// A payload it accepts is being dragged
+    pub valid: Memo<bool>,
+    /// That payload is over it
+    pub over: Memo<bool>,
+}
+
+/// Lets `cx` receive payloads of type `P` that pass `accept`
+pub fn drop_target<P: 'static>(
+    cx: Scope,
+    accept: impl Fn(&P) -> bool + 'static,
+    on_drop: impl Fn(&P) + 'static,
+) -> DropTarget {
+    let dd = use_context::<DragDrop>(cx).unwrap();
+
+    let accept: Accept = Rc::new(move |v: &dyn Any| v.downcast_ref::<P>().is_some_and(&accept));
+    let drop: OnDrop = Rc::new(move |v: &dyn Any| {
+        if let Some(v) = v.downcast_ref::<P>() {
+            on_drop(v)
+        }
+    });
+
+    let id = dd
+        .targets
+        .try_update_value(|t| {
+            let id = t.next;
+            t.next += 1;
+            t.list.push(Target {
+                id,
+                scope: cx.id(),
+                accept: Rc::clone(&accept),
+                drop,
+            });
+            id
+        })
+        .unwrap();
+
+    on_cleanup(cx, move || {
+        dd.targets
+            .try_update_value(|t| t.list.retain(|v| v.id != id));
+    });
+
+    let valid = create_memo(cx, move |_| {
+        dd.active
+            .with(|a| a.as_ref().is_some_and(|a| accept(&*a.payload)))
+    });
+    let over = create_memo(cx, move |_| {
+        let position = dd.active.with(|a| a.as_ref().map(|a| a.position));
+
+        match position {
+            Some((x, y)) if valid() => dd
+                .handlers
+                .hits(x, y)
+                .iter()
+                .flatten()
+                .any(|v| *v == cx.id()),
+            _ => false,
+        }
+    });
+
+    DropTarget { valid, over }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use crossterm::event::{Event, MouseButton, MouseEventKind};
+    use leptos_reactive::*;
+    use tui::{layout::Rect, widgets::Paragraph};
+
+    use crate::bootstrapper::{
+        assert_rb, mouse,
+        shared_ctx::{EventDispatcher, RenderBase, RenderBaseAuto, RenderCounter},
+        test_bootstrap, test_bootstrap_backend, RecordingBackend,
+    };
+
+    use super::{draggable, drop_target, on_drag};
+
+    #[test]
+    fn drags_end_without_a_release() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let inner = Rc::clone(&log);
+
+        test_bootstrap(
+            move |cx| {
+                let ed = use_context::<EventDispatcher>(cx).unwrap();
+                let rc = use_context::<RenderCounter>(cx).unwrap();
+                let rb: RenderBaseAuto = use_context(cx).unwrap();
+
+                let _ = cx.run_child_scope(|cx| {
+                    rb.render(cx, Paragraph::new("grip"), Rect::new(0, 0, 4, 1));
+                    on_drag(cx, move |d| inner.borrow_mut().push((d.position, d.done)));
+                });
+
+                let down = MouseEventKind::Down(MouseButton::Left);
+                let drag = MouseEventKind::Drag(MouseButton::Left);
+
+                create_effect(cx, move |_| {
+                    if rc.0.get() == 0 {
+                        for e in [
+                            mouse(down, 1, 0),
+                            mouse(drag, 8, 3),
+                            // Released outside, back without the button
+                            mouse(MouseEventKind::Moved, 6, 2),
+                            mouse(down, 2, 0),
+                            mouse(drag, 8, 3),
+                            Event::FocusLost,
+                            mouse(drag, 5, 5),
+                        ] {
+                            ed.dispatch(e).unwrap();
+                        }
+                    }
+                });
+            },
+            10,
+            6,
+            Some(8),
+        )
+        .unwrap();
+
+        assert_eq!(
+            *log.borrow(),
+            [
+                ((1, 0), false),
+                ((8, 3), false),
+                ((6, 2), true),
+                ((2, 0), false),
+                ((8, 3), false),
+                ((8, 3), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn ghost_stays_inside_the_region() {
+        test_bootstrap(
+            move |cx| {
+                let ed = use_context::<EventDispatcher>(cx).unwrap();
+                let rc = use_context::<RenderCounter>(cx).unwrap();
+                let rb: RenderBaseAuto = use_context(cx).unwrap();
+
+                let item = rb.clone();
+                let _ = cx.run_child_scope(|cx| {
+                    create_effect(cx, move |_| {
+                        item.render(cx, Paragraph::new("7"), Rect::new(0, 0, 1, 1))
+                    });
+                    draggable(cx, "seven", || 7u32);
+                });
+
+                create_effect(cx, move |_| match rc.0.get() {
+                    0 => {
+                        ed.dispatch(mouse(MouseEventKind::Down(MouseButton::Left), 0, 0))
+                            .unwrap();
+                        // Below the viewport, then cut off at its right edge
+                        ed.dispatch(mouse(MouseEventKind::Drag(MouseButton::Left), 2, 9))
+                            .unwrap();
+                    }
+                    2 => ed
+                        .dispatch(mouse(MouseEventKind::Drag(MouseButton::Left), 7, 1))
+                        .unwrap(),
+                    4 => assert_rb(&rb, "drag-ghost-clipped"),
+                    _ => (),
+                });
+            },
+            10,
+            3,
+            Some(5),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn ghost_renders_on_any_backend() {
+        let (backend, rec) = RecordingBackend::new(10, 3);
+
+        test_bootstrap_backend(
+            move |cx| {
+                let ed = use_context::<EventDispatcher>(cx).unwrap();
+                let rc = use_context::<RenderCounter>(cx).unwrap();
+                let rb: RenderBase<RecordingBackend> = use_context(cx).unwrap();
+
+                let _ = cx.run_child_scope(|cx| {
+                    rb.render(cx, Paragraph::new("7"), Rect::new(0, 0, 1, 1));
+                    draggable(cx, "seven", || 7u32);
+                });
+
+                create_effect(cx, move |_| {
+                    if rc.0.get() == 0 {
+                        ed.dispatch(mouse(MouseEventKind::Down(MouseButton::Left), 0, 0))
+                            .unwrap();
+                        ed.dispatch(mouse(MouseEventKind::Drag(MouseButton::Left), 2, 1))
+                            .unwrap();
+                    }
+                });
+            },
+            backend,
+            Some(3),
+        )
+        .unwrap();
+
+        assert!(String::from_utf8(rec.take()).unwrap().contains("seven"));
+    }
+
+    #[test]
+    fn drags_onto_accepting_target() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let inner = Rc::clone(&log);
+
+        test_bootstrap(
+            move |cx| {
+                let ed = use_context::<EventDispatcher>(cx).unwrap();
+                let rc = use_context::<RenderCounter>(cx).unwrap();
+                let rb: RenderBaseAuto = use_context(cx).unwrap();
+
+                let item = rb.clone();
+                let (_, _) = cx.run_child_scope(|cx| {
+                    create_effect(cx, move |_| {
+                        item.render(cx, Paragraph::new("7"), Rect::new(0, 0, 1, 1))
+                    });
+                    draggable(cx, "seven", || 7u32);
+                });
+
+                let base = rb.clone();
+                let target = move |name: &'static str, area, accept: fn(&u32) -> bool| {
+                    let log = Rc::clone(&inner);
+                    let rb = base.clone();
+
+                    cx.run_child_scope(move |cx| {
+                        create_effect(cx, move |_| rb.render(cx, Paragraph::new(name), area));
+                        drop_target(cx, accept, move |v: &u32| {
+                            log.borrow_mut().push(format!("{} {}", name, v))
+                        })
+                    })
+                    .0
+                };
+                let even = target("even", Rect::new(0, 4, 4, 2), |v| v % 2 == 0);
+                let any = target("any", Rect::new(5, 4, 4, 2), |_| true);
+
+                create_effect(cx, move |_| match rc.0.get() {
+                    0 => {
+                        for e in [
+                            mouse(MouseEventKind::Down(MouseButton::Left), 0, 0),
+                            mouse(MouseEventKind::Drag(MouseButton::Left), 6, 4),
+                            // Lets a frame clear the ghost at the origin
+                            Event::FocusGained,
+                            mouse(MouseEventKind::Up(MouseButton::Left), 6, 4),
+                            mouse(MouseEventKind::Down(MouseButton::Left), 0, 0),
+                            mouse(MouseEventKind::Up(MouseButton::Left), 1, 4),
+                        ] {
+                            ed.dispatch(e).unwrap();
+                        }
+                    }
+                    3 => {
+                        assert!(!even.valid.get_untracked());
+                        assert!(any.valid.get_untracked());
+                        assert!(any.over.get_untracked());
+
+                        assert_rb(&rb, "drag-ghost");
+                    }
+                    4 => assert!(!any.valid.get_untracked()),
+                    _ => (),
+                });
+            },
+            10,
+            6,
+            Some(7),
+        )
+        .unwrap();
+
+        assert_eq!(*log.borrow(), ["any 7"]);
+    }
+}
@@ -8,7 +8,7 @@ use crossterm::event::{self, Event};
 use leptos_reactive::*;
 use tui::{backend::Backend, layout::Rect};
 
-use super::RenderBase;
+use super::{super::Screen, RenderBase};
 
 #[derive(Clone)]
 pub struct EventDispatcher(mpsc::Sender<Event>);
@@ -42,49 +42,170 @@ impl EventQueue {
 
         base
     }
+    /// Calls `f` with every event dispatched from now on, skipping the one
+    /// the queue holds already. `f` runs untracked, as a signal it reads
+    /// would otherwise deliver the same event again whenever it changes.
+    pub fn listen(self, cx: Scope, f: impl Fn(&Event) + 'static) {
+        create_effect(cx, move |prev: Option<()>| {
+            let e = self.0.get();
+            if prev.is_some() {
+                cx.untrack(|| f(&e))
+            }
+        });
+    }
     fn dispatch<B: Backend>(
         eq: &RwSignal<Event>,
         e: Event,
         region: &RwSignal<Rect>,
         rb: RenderBase<B>,
+        screen: Screen,
     ) -> Result<(), Box<dyn Error>> {
         Ok(match e {
-            Event::Resize(h, w) => {
-                let rect = tui::layout::Rect::new(0, 0, w, h);
+            Event::Resize(w, h) => {
+                let rect =
+                    screen.viewport(tui::layout::Rect::new(0, 0, w, h), region.get_untracked());
                 {
                     let rb = rb.access();
                     let mut rb = rb.lock().unwrap();
                     rb.1.resize(rect);
-                    rb.0.resize(rect)?;
+                    match screen {
+                        Screen::Alternate => rb.0.resize(rect)?,
+                        // Clearing the screen would wipe the output above the
+                        // viewport, the repaint below blanks only its rows
+                        Screen::Inline(_) => rb.0.current_buffer_mut().resize(rect),
+                    }
+
+                    // Resizing only truncates or extends the cells, which
+                    // would leave the last frame reflowed into the new size
+                    rb.0.current_buffer_mut().reset();
                 }
 
                 region.set(rect);
-                eq.set(Event::Resize(h, w));
+                // Components that do not depend on the region have to be
+                // drawn into the blank buffer again as well
+                rb.redraw_all();
+                eq.set(Event::Resize(w, h));
             }
             e => eq.set(e),
         })
     }
+
+    // A burst of resizes, e.g. while dragging the window, is applied once
+    fn coalesce(
+        e: Event,
+        mut next: impl FnMut() -> Result<Option<Event>, Box<dyn Error>>,
+        mut dispatch: impl FnMut(Event) -> Result<(), Box<dyn Error>>,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut e = e;
+
+        while let Event::Resize(..) = e {
+            match next()? {
+                Some(n @ Event::Resize(..)) => e = n,
+                Some(n) => {
+                    dispatch(e)?;
+                    e = n;
+                }
+                None => break,
+            }
+        }
+
+        dispatch(e)
+    }
     pub fn test_poll<B: Backend>(
         eq: &RwSignal<Event>,
         rec: &mpsc::Receiver<Event>,
         region: &RwSignal<Rect>,
         rb: RenderBase<B>,
+        screen: Screen,
     ) -> Result<(), Box<dyn Error>> {
         Ok(match rec.recv_timeout(Duration::from_millis(1)) {
-            Ok(e) => Self::dispatch(eq, e, region, rb)?,
+            Ok(e) => Self::coalesce(
+                e,
+                || Ok(rec.try_recv().ok()),
+                |e| Self::dispatch(eq, e, region, rb.clone(), screen),
+            )?,
             Err(e) => match e {
                 mpsc::RecvTimeoutError::Timeout => (),
                 mpsc::RecvTimeoutError::Disconnected => Err(e)?,
             },
         })
     }
+    /// Waits up to `tick_rate` for an event, returns whether there was one.
+    /// Events sent through the [`EventDispatcher`] go first.
     pub fn poll<B: Backend>(
         eq: &RwSignal<Event>,
+        rec: &mpsc::Receiver<Event>,
         region: &RwSignal<Rect>,
         rb: RenderBase<B>,
-    ) -> Result<(), Box<dyn Error>> {
-        Ok(if event::poll(Duration::from_millis(10))? {
-            Self::dispatch(eq, event::read()?, region, rb)?
+        screen: Screen,
+        tick_rate: Duration,
+        debounce: Duration,
+    ) -> Result<bool, Box<dyn Error>> {
+        if let Ok(e) = rec.try_recv() {
+            Self::dispatch(eq, e, region, rb, screen)?;
+            return Ok(true);
+        }
+
+        if !event::poll(tick_rate)? {
+            return Ok(false);
+        }
+
+        Self::coalesce(
+            event::read()?,
+            || Ok(event::poll(debounce)?.then(event::read).transpose()?),
+            |e| Self::dispatch(eq, e, region, rb.clone(), screen),
+        )?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crossterm::event::Event;
+    use leptos_reactive::*;
+    use tui::{
+        layout::Rect,
+        terminal::{TerminalOptions, Viewport},
+        Terminal,
+    };
+
+    use crate::bootstrapper::{prefix_sum_2d::PrefixSum2d, RecordingBackend, Screen};
+
+    use super::{super::RenderBase, EventQueue};
+
+    #[test]
+    fn inline_resize_keeps_the_output_above() {
+        create_scope(create_runtime(), |cx| {
+            let (backend, rec) = RecordingBackend::new(10, 6);
+            let area = Rect::new(0, 3, 10, 3);
+            let terminal = Terminal::with_options(
+                backend,
+                TerminalOptions {
+                    viewport: Viewport::fixed(area),
+                },
+            )
+            .unwrap();
+            let terminal = Arc::new(Mutex::new((terminal, PrefixSum2d::new(area))));
+            RenderBase::attach(cx, Arc::clone(&terminal));
+
+            let rb: RenderBase<RecordingBackend> = use_context(cx).unwrap();
+            let eq = create_rw_signal(cx, Event::FocusGained);
+            let region = create_rw_signal(cx, area);
+
+            let resize = Event::Resize(8, 6);
+            EventQueue::dispatch(&eq, resize, &region, rb.clone(), Screen::Inline(3)).unwrap();
+            rb.do_frame().unwrap();
+
+            assert_eq!(region.get_untracked(), Rect::new(0, 3, 8, 3));
+
+            // The viewport rows are drawn blank instead of clearing the screen
+            let out = String::from_utf8(rec.take()).unwrap();
+            assert!(!out.contains("\x1b[2J"));
+            assert!(out.contains("\x1b[4;1H"));
+            assert!(!out.contains("\x1b[3;1H"));
         })
+        .dispose();
     }
 }
@@ -0,0 +1,279 @@
+use std::collections::BTreeMap;
+
+use crossterm::event::KeyEvent;
+use leptos_reactive::*;
+use tui::style::Style;
+
+use super::{EventHandlers, Propagation};
+
+struct Node {
+    parent: Option<usize>,
+}
+
+#[derive(Default)]
+struct FocusTree {
+    next: usize,
+    // Keyed by registration, so siblings are ordered like they were mounted
+    nodes: BTreeMap<usize, Node>,
+}
+
+impl FocusTree {
+    /// Every node, parents before their children
+    fn order(&self) -> Vec<usize> {
+        fn visit(tree: &FocusTree, parent: Option<usize>, out: &mut Vec<usize>) {
+            for (&id, _) in tree.nodes.iter().filter(|(_, n)| n.parent == parent) {
+                out.push(id);
+                visit(tree, Some(id), out);
+            }
+        }
+
+        let mut out = Vec::with_capacity(self.nodes.len());
+        visit(self, None, &mut out);
+        out
+    }
+
+    /// `id` followed by its ancestors
+    fn path(&self, id: usize) -> Vec<usize> {
+        std::iter::successors(Some(id), |id| self.nodes.get(id)?.parent)
+            .filter(|id| self.nodes.contains_key(id))
+            .collect()
+    }
+}
+
+/// Tracks which component receives key events, moved with Tab and Shift-Tab
+/// once no handler stops them, see [`EventHandlers`]
+#[derive(Clone, Copy)]
+pub struct FocusManager {
+    tree: StoredValue<FocusTree>,
+    focused: RwSignal<Option<usize>>,
+    ring: RwSignal<Style>,
+}
+
+impl FocusManager {
+    pub fn attach(cx: Scope, ring: Style) -> Self {
+        let v = Self {
+            tree: store_value(cx, Default::default()),
+            focused: create_rw_signal(cx, None),
+            ring: create_rw_signal(cx, ring),
+        };
+        provide_context(cx, v);
+        v
+    }
+
+    /// The focused component followed by its ancestors
+    pub(super) fn path(&self) -> Vec<usize> {
+        match self.focused.get_untracked() {
+            Some(f) => self.tree.with_value(|t| t.path(f)),
+            None => vec![],
+        }
+    }
+
+    /// Moves the focus `by` components in focus order, wrapping around
+    pub fn step(&self, by: isize) {
+        let order = self.tree.with_value(FocusTree::order);
+        if order.is_empty() {
+            return;
+        }
+
+        let len = order.len() as isize;
+        let at = self
+            .focused
+            .get_untracked()
+            .and_then(|f| order.iter().position(|v| *v == f))
+            .map(|i| (i as isize + by).rem_euclid(len))
+            .unwrap_or(if by < 0 { len - 1 } else { 0 });
+
+        self.focused.set(Some(order[at as usize]));
+    }
+
+    pub fn blur(&self) {
+        self.focused.set(None)
+    }
+
+    /// The style focused components draw their ring with
+    pub fn ring_style(&self) -> RwSignal<Style> {
+        self.ring
+    }
+}
+
+/// A component that can take the focus
+#[derive(Clone, Copy)]
+pub struct Focusable {
+    id: usize,
+    manager: FocusManager,
+    handlers: EventHandlers,
+
+    focused: Memo<bool>,
+    within: Memo<bool>,
+}
+
+impl Focusable {
+    pub(super) fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn focus(&self) {
+        self.manager.focused.set(Some(self.id))
+    }
+
+    pub fn is_focused(&self) -> Signal<bool> {
+        self.focused.into()
+    }
+
+    /// Whether the focus is on this component or one of its descendants
+    pub fn contains_focus(&self) -> Signal<bool> {
+        self.within.into()
+    }
+
+    /// The focus ring while focused, an empty style otherwise
+    pub fn ring(&self) -> Style {
+        if self.focused.get() {
+            self.manager.ring.get()
+        } else {
+            Style::default()
+        }
+    }
+
+    /// Handles every key press while this component or a descendant is
+    /// focused
+    pub fn on_key(&self, handler: impl Fn(&KeyEvent) -> Propagation + 'static) {
+        self.handlers.any_key(Some(self.id), false, handler)
+    }
+}
+
+/// Registers `cx` as focusable, nested below the closest focusable ancestor.
+/// Scopes below `cx` see the returned [`Focusable`] as their parent.
+pub fn use_focusable(cx: Scope) -> Focusable {
+    let manager = use_context::<FocusManager>(cx).unwrap();
+    let handlers = use_context::<EventHandlers>(cx).unwrap();
+    let parent = use_context::<Focusable>(cx).map(|v| v.id);
+
+    let id = manager
+        .tree
+        .try_update_value(|t| {
+            let id = t.next;
+            t.next += 1;
+            t.nodes.insert(id, Node { parent });
+            id
+        })
+        .unwrap();
+
+    let focused = create_memo(cx, move |_| manager.focused.get() == Some(id));
+    let within = create_memo(cx, move |_| match manager.focused.get() {
+        Some(f) => manager.tree.with_value(|t| t.path(f).contains(&id)),
+        None => false,
+    });
+
+    on_cleanup(cx, move || {
+        handlers.remove_owned(id);
+        manager.tree.try_update_value(|t| {
+            t.nodes.remove(&id);
+
+            // Children are only reachable through their parent
+            for node in t.nodes.values_mut() {
+                if node.parent == Some(id) {
+                    node.parent = parent;
+                }
+            }
+        });
+
+        // The focus falls back to the closest ancestor, unless the manager
+        // is being disposed as well
+        let focused = manager.focused.try_update_untracked(|f| *f == Some(id));
+        if focused == Some(true) {
+            manager.focused.set(parent);
+        }
+    });
+
+    let v = Focusable {
+        id,
+        manager,
+        handlers,
+        focused,
+        within,
+    };
+    provide_context(cx, v);
+    v
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+    use leptos_reactive::*;
+
+    use crate::bootstrapper::{
+        shared_ctx::{EventDispatcher, RenderCounter},
+        test_bootstrap,
+    };
+
+    use super::{use_focusable, Propagation};
+
+    #[test]
+    fn tab_order_and_bubbling() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let inner = Rc::clone(&log);
+
+        test_bootstrap(
+            move |cx| {
+                let ed = use_context::<EventDispatcher>(cx).unwrap();
+                let rc = use_context::<RenderCounter>(cx).unwrap();
+
+                let a = use_focusable(cx);
+                let (b, _) = cx.run_child_scope(use_focusable);
+                let (c, _) = cx.run_child_scope(use_focusable);
+
+                let log = Rc::clone(&inner);
+                a.on_key(move |k| {
+                    log.borrow_mut().push(format!("a {:?}", k.code));
+
+                    match k.code {
+                        KeyCode::Char('x') => Propagation::Stop,
+                        _ => Propagation::Continue,
+                    }
+                });
+                let log = Rc::clone(&inner);
+                c.on_key(move |k| {
+                    log.borrow_mut().push(format!("c {:?}", k.code));
+                    Propagation::Continue
+                });
+
+                let key = |code| Event::Key(KeyEvent::new(code, KeyModifiers::NONE));
+
+                create_effect(cx, move |_| match rc.0.get() {
+                    0 => {
+                        for code in [KeyCode::Tab, KeyCode::Tab, KeyCode::Tab, KeyCode::BackTab] {
+                            ed.dispatch(key(code)).unwrap();
+                        }
+                    }
+                    4 => {
+                        assert!(b.is_focused().get_untracked());
+                        assert!(a.contains_focus().get_untracked());
+
+                        c.focus();
+                        ed.dispatch(key(KeyCode::Char('x'))).unwrap();
+                    }
+                    5 => assert!(c.is_focused().get_untracked()),
+                    _ => (),
+                });
+            },
+            10,
+            10,
+            Some(6),
+        )
+        .unwrap();
+
+        assert_eq!(
+            *log.borrow(),
+            [
+                "a Tab",
+                "a Tab",
+                "c BackTab",
+                "a BackTab",
+                "c Char('x')",
+                "a Char('x')"
+            ]
+        );
+    }
+}
@@ -0,0 +1,472 @@
+use std::{cell::Cell, rc::Rc, time::Duration, time::Instant};
+
+use crossterm::event::{Event, MouseButton, MouseEvent, MouseEventKind};
+use leptos_reactive::*;
+
+use super::{EventHandlers, EventQueue, RenderCounter};
+
+/// The time gestures are measured with, the test bootstrapper advances it
+/// by hand
+#[derive(Clone)]
+pub enum Clock {
+    System(Instant),
+    Virtual(Rc<Cell<Duration>>),
+}
+
+impl Clock {
+    pub fn attach(cx: Scope, clock: Clock) -> Self {
+        provide_context(cx, clock.clone());
+        clock
+    }
+
+    /// The time since the clock started
+    pub fn now(&self) -> Duration {
+        match self {
+            Clock::System(start) => start.elapsed(),
+            Clock::Virtual(now) => now.get(),
+        }
+    }
+
+    /// Moves a virtual clock forward, a system clock ignores it
+    pub fn advance(&self, by: Duration) {
+        if let Clock::Virtual(now) = self {
+            now.set(now.get() + by)
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct GestureConfig {
+    /// The longest time between clicks counted together
+    pub multi_click_interval: Duration,
+    /// The farthest clicks counted together can be apart, in cells
+    pub multi_click_distance: u16,
+    pub long_press: Duration,
+
+    /// Wheel events closer together than this speed up scrolling
+    pub wheel_window: Duration,
+    /// Added to the delta for every event in a streak
+    pub wheel_acceleration: f32,
+    pub wheel_max: u16,
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        Self {
+            multi_click_interval: Duration::from_millis(400),
+            multi_click_distance: 1,
+            long_press: Duration::from_millis(500),
+            wheel_window: Duration::from_millis(50),
+            wheel_acceleration: 0.5,
+            wheel_max: 8,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Gesture {
+    /// Sent on press, `count` goes up to 3 for triple clicks
+    Click {
+        button: MouseButton,
+        count: u8,
+        column: u16,
+        row: u16,
+    },
+    LongPress {
+        column: u16,
+        row: u16,
+    },
+    /// Lines to scroll by, positive is down
+    Scroll {
+        delta: i32,
+        column: u16,
+        row: u16,
+    },
+}
+
+impl Gesture {
+    pub fn position(&self) -> (u16, u16) {
+        match *self {
+            Self::Click { column, row, .. }
+            | Self::LongPress { column, row }
+            | Self::Scroll { column, row, .. } => (column, row),
+        }
+    }
+
+    /// The same gesture at another position
+    pub fn at(mut self, x: u16, y: u16) -> Self {
+        match &mut self {
+            Self::Click { column, row, .. }
+            | Self::LongPress { column, row }
+            | Self::Scroll { column, row, .. } => (*column, *row) = (x, y),
+        }
+        self
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Press {
+    button: MouseButton,
+    at: Duration,
+    position: (u16, u16),
+    count: u8,
+    // Whether the press can still become a long press
+    held: bool,
+}
+
+#[derive(Default)]
+struct Recognizer {
+    press: Option<Press>,
+    wheel: Option<(Duration, bool, u16)>,
+}
+
+/// Clicks, long presses and accelerated scrolling, recognized from the raw
+/// mouse events of the [`EventQueue`]. Each is routed to the
+/// [`on_gesture`](super::on_gesture) handlers under it, the signal holds the
+/// last one in absolute coordinates.
+#[derive(Clone, Copy)]
+pub struct Gestures(pub ReadSignal<Option<Gesture>>);
+
+impl std::ops::Deref for Gestures {
+    type Target = ReadSignal<Option<Gesture>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Gestures {
+    pub fn attach(cx: Scope, config: GestureConfig) -> Self {
+        let out = create_rw_signal(cx, None);
+        let v = Self(out.read_only());
+        provide_context(cx, v);
+
+        let eq: EventQueue = use_context(cx).unwrap();
+        let rc: RenderCounter = use_context(cx).unwrap();
+        let clock: Clock = use_context(cx).unwrap();
+        let handlers: EventHandlers = use_context(cx).unwrap();
+        let state = store_value(cx, Recognizer::default());
+
+        let emit = move |g: Gesture| {
+            out.set(Some(g));
+            handlers.gesture(g);
+        };
+
+        {
+            let clock = clock.clone();
+            eq.listen(cx, move |e| {
+                let Event::Mouse(m) = e else { return };
+
+                let gesture = state
+                    .try_update_value(|s| s.mouse(&config, clock.now(), m))
+                    .flatten();
+
+                if let Some(g) = gesture {
+                    emit(g)
+                }
+            });
+        }
+
+        // Long presses have no event of their own, so every tick checks
+        create_effect(cx, move |_| {
+            rc.0.get();
+
+            let now = clock.now();
+
+            // Only the tick is tracked, not what the handlers read
+            cx.untrack(|| {
+                let gesture = state
+                    .try_update_value(|s| {
+                        let press = s.press.as_mut().filter(|p| p.held)?;
+                        if now.saturating_sub(press.at) < config.long_press {
+                            return None;
+                        }
+
+                        press.held = false;
+                        let (column, row) = press.position;
+                        Some(Gesture::LongPress { column, row })
+                    })
+                    .flatten();
+
+                if let Some(g) = gesture {
+                    emit(g)
+                }
+            })
+        });
+
+        v
+    }
+}
+
+impl Recognizer {
+    fn mouse(&mut self, config: &GestureConfig, now: Duration, m: &MouseEvent) -> Option<Gesture> {
+        let (column, row) = (m.column, m.row);
+        let near = |(x, y): (u16, u16)| {
+            x.abs_diff(column) <= config.multi_click_distance
+                && y.abs_diff(row) <= config.multi_click_distance
+        };
+
+        match m.kind {
+            MouseEventKind::Down(button) => {
+                let count = match self.press {
+                    Some(p)
+                        if p.button == button
+                            && now.saturating_sub(p.at) <= config.multi_click_interval
+                            && near(p.position) =>
+                    {
+                        p.count % 3 + 1
+                    }
+                    _ => 1,
+                };
+
+                self.press = Some(Press {
+                    button,
+                    at: now,
+                    position: (column, row),
+                    count,
+                    held: true,
+                });
+
+                Some(Gesture::Click {
+                    button,
+                    count,
+                    column,
+                    row,
+                })
+            }
+            MouseEventKind::Up(_) => {
+                if let Some(p) = &mut self.press {
+                    p.held = false;
+                }
+                None
+            }
+            MouseEventKind::Drag(_) => {
+                if let Some(p) = self.press.as_mut().filter(|p| !near(p.position)) {
+                    p.held = false;
+                }
+                None
+            }
+            MouseEventKind::ScrollDown | MouseEventKind::ScrollUp => {
+                let down = m.kind == MouseEventKind::ScrollDown;
+
+                let streak = match self.wheel {
+                    Some((at, d, streak))
+                        if d == down && now.saturating_sub(at) <= config.wheel_window =>
+                    {
+                        streak + 1
+                    }
+                    _ => 0,
+                };
+                self.wheel = Some((now, down, streak));
+
+                let delta = 1 + (streak as f32 * config.wheel_acceleration) as i32;
+                let delta = std::cmp::min(delta, config.wheel_max as i32);
+
+                Some(Gesture::Scroll {
+                    delta: if down { delta } else { -delta },
+                    column,
+                    row,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc, time::Duration};
+
+    use crossterm::event::{MouseButton, MouseEventKind};
+    use leptos_reactive::*;
+
+    use tui::{layout::Rect, widgets::Paragraph};
+
+    use crate::bootstrapper::{
+        mouse,
+        shared_ctx::{on_gesture, EventDispatcher, Propagation, RenderBaseAuto, RenderCounter},
+        test_bootstrap,
+    };
+
+    use super::{Clock, Gesture, Gestures};
+
+    #[test]
+    fn counts_clicks_on_a_virtual_clock() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let inner = Rc::clone(&log);
+
+        test_bootstrap(
+            move |cx| {
+                let ed = use_context::<EventDispatcher>(cx).unwrap();
+                let rc = use_context::<RenderCounter>(cx).unwrap();
+                let clock = use_context::<Clock>(cx).unwrap();
+                let gestures = use_context::<Gestures>(cx).unwrap();
+
+                create_effect(cx, move |_| {
+                    if let Some(g) = gestures.get() {
+                        inner.borrow_mut().push(g)
+                    }
+                });
+
+                let down = |column| mouse(MouseEventKind::Down(MouseButton::Left), column, 0);
+                let up = |column| mouse(MouseEventKind::Up(MouseButton::Left), column, 0);
+                let wheel = mouse(MouseEventKind::ScrollDown, 0, 0);
+
+                let ms = Duration::from_millis;
+                create_effect(cx, move |_| {
+                    let events = match rc.0.get() {
+                        0 => vec![down(0), up(0)],
+                        2 => {
+                            clock.advance(ms(100));
+                            vec![down(1), up(1)]
+                        }
+                        4 => {
+                            clock.advance(ms(100));
+                            vec![down(1), up(1)]
+                        }
+                        6 => {
+                            clock.advance(ms(1000));
+                            vec![down(1)]
+                        }
+                        7 => {
+                            // Held until the next tick notices
+                            clock.advance(ms(600));
+                            vec![]
+                        }
+                        8 => vec![up(1), wheel.clone(), wheel.clone(), wheel.clone()],
+                        _ => vec![],
+                    };
+
+                    for e in events {
+                        ed.dispatch(e).unwrap();
+                    }
+                });
+            },
+            10,
+            10,
+            Some(13),
+        )
+        .unwrap();
+
+        let click = |count, column| Gesture::Click {
+            button: MouseButton::Left,
+            count,
+            column,
+            row: 0,
+        };
+        let scroll = |delta| Gesture::Scroll {
+            delta,
+            column: 0,
+            row: 0,
+        };
+
+        assert_eq!(
+            *log.borrow(),
+            [
+                click(1, 0),
+                click(2, 1),
+                click(3, 1),
+                click(1, 1),
+                Gesture::LongPress { column: 1, row: 0 },
+                scroll(1),
+                scroll(1),
+                scroll(2),
+            ]
+        );
+    }
+
+    #[test]
+    fn routes_to_the_scope_under_it() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let inner = Rc::clone(&log);
+
+        test_bootstrap(
+            move |cx| {
+                let ed = use_context::<EventDispatcher>(cx).unwrap();
+                let rc = use_context::<RenderCounter>(cx).unwrap();
+                let rb: RenderBaseAuto = use_context(cx).unwrap();
+
+                let _ = cx.run_child_scope(|cx| {
+                    rb.render(cx, Paragraph::new("list"), Rect::new(2, 1, 4, 2));
+                    on_gesture(cx, move |g| {
+                        inner.borrow_mut().push(*g);
+                        Propagation::Stop
+                    });
+                });
+
+                create_effect(cx, move |_| {
+                    if rc.0.get() == 0 {
+                        for e in [
+                            mouse(MouseEventKind::Down(MouseButton::Right), 3, 2),
+                            mouse(MouseEventKind::ScrollUp, 8, 2),
+                            mouse(MouseEventKind::ScrollDown, 5, 2),
+                        ] {
+                            ed.dispatch(e).unwrap();
+                        }
+                    }
+                });
+            },
+            10,
+            4,
+            Some(4),
+        )
+        .unwrap();
+
+        assert_eq!(
+            *log.borrow(),
+            [
+                Gesture::Click {
+                    button: MouseButton::Right,
+                    count: 1,
+                    column: 1,
+                    row: 1,
+                },
+                Gesture::Scroll {
+                    delta: 1,
+                    column: 3,
+                    row: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn signals_read_by_handlers_do_not_repeat_clicks() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let inner = Rc::clone(&log);
+
+        test_bootstrap(
+            move |cx| {
+                let ed = use_context::<EventDispatcher>(cx).unwrap();
+                let rc = use_context::<RenderCounter>(cx).unwrap();
+                let rb: RenderBaseAuto = use_context(cx).unwrap();
+                let seen = create_rw_signal(cx, 0);
+
+                rb.render(cx, Paragraph::new("list"), Rect::new(0, 0, 4, 1));
+                on_gesture(cx, move |g| {
+                    inner.borrow_mut().push((*g, seen.get()));
+                    Propagation::Stop
+                });
+
+                let down = mouse(MouseEventKind::Down(MouseButton::Left), 1, 0);
+                create_effect(cx, move |_| match rc.0.get() {
+                    0 => ed.dispatch(down.clone()).unwrap(),
+                    2 => seen.set(1),
+                    _ => (),
+                });
+            },
+            10,
+            4,
+            Some(4),
+        )
+        .unwrap();
+
+        let click = Gesture::Click {
+            button: MouseButton::Left,
+            count: 1,
+            column: 1,
+            row: 0,
+        };
+        assert_eq!(*log.borrow(), [(click, 0)]);
+    }
+}
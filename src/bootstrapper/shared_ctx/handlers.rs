@@ -0,0 +1,692 @@
+use std::{cell::RefCell, collections::HashSet, rc::Rc};
+
+use crossterm::event::{
+    Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use leptos_reactive::*;
+use tui::{backend::Backend, layout::Rect};
+
+use crate::bootstrapper::mounts::Mounts;
+
+use super::{EventQueue, FocusManager, Focusable, Gesture, RenderBase};
+
+/// Whether an event goes on to the next handler
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Propagation {
+    Continue,
+    Stop,
+}
+
+#[derive(Clone)]
+enum Handler {
+    /// `None` handles every key, releases are only heard when asked for
+    Key(Option<KeyCode>, bool, Rc<dyn Fn(&KeyEvent) -> Propagation>),
+    Mouse(MaybeSignal<Rect>, Rc<dyn Fn(&MouseEvent) -> Propagation>),
+    /// Hears the pointer through anything rendered by the scope or its
+    /// descendants
+    Pointer(ScopeId, Rc<dyn Fn(&MouseEvent) -> Propagation>),
+    Gesture(ScopeId, Rc<dyn Fn(&Gesture) -> Propagation>),
+    Paste(Rc<dyn Fn(&str) -> Propagation>),
+    Resize(Rc<dyn Fn(u16, u16)>),
+}
+
+struct Entry {
+    id: usize,
+    // The focusable the handler was registered under, which it only hears
+    // keys and pastes through while it is focused or contains the focus
+    owner: Option<usize>,
+    handler: Handler,
+}
+
+#[derive(Default)]
+struct Registry {
+    next: usize,
+    entries: Vec<Entry>,
+}
+
+type CaptureFn = Rc<dyn Fn(&MouseEvent)>;
+
+#[derive(Default)]
+struct Capture {
+    next: usize,
+    current: Option<(usize, CaptureFn)>,
+}
+
+/// Delivers every event to the handlers registered for it, once
+#[derive(Clone, Copy)]
+pub struct EventHandlers {
+    registry: StoredValue<Registry>,
+    focus: FocusManager,
+    mounts: StoredValue<Rc<RefCell<Mounts>>>,
+
+    // The topmost scope under the pointer followed by its ancestors
+    pub(super) hovered: RwSignal<Vec<ScopeId>>,
+
+    // Where the pointer is, as mouse handlers only see local positions
+    pointer: StoredValue<(u16, u16)>,
+    // Receives every mouse event while set, until the button is released
+    capture: StoredValue<Capture>,
+}
+
+impl EventHandlers {
+    pub fn attach<B: Backend + 'static>(cx: Scope) -> Self {
+        let rb = use_context::<RenderBase<B>>(cx).unwrap();
+
+        let v = Self {
+            registry: store_value(cx, Default::default()),
+            focus: use_context(cx).unwrap(),
+            mounts: store_value(cx, rb.mounts),
+            hovered: create_rw_signal(cx, vec![]),
+            pointer: store_value(cx, (0, 0)),
+            capture: store_value(cx, Default::default()),
+        };
+        provide_context(cx, v);
+
+        let eq: EventQueue = use_context(cx).unwrap();
+        eq.listen(cx, move |e| v.dispatch(e));
+
+        v
+    }
+
+    fn register(&self, owner: Option<usize>, handler: Handler) -> usize {
+        self.registry
+            .try_update_value(|r| {
+                let id = r.next;
+                r.next += 1;
+                r.entries.push(Entry { id, owner, handler });
+                id
+            })
+            .unwrap()
+    }
+
+    fn register_in(&self, cx: Scope, handler: Handler) {
+        let owner = use_context::<Focusable>(cx).map(|f| f.id());
+        let id = self.register(owner, handler);
+
+        let registry = self.registry;
+        on_cleanup(cx, move || {
+            registry.try_update_value(|r| r.entries.retain(|e| e.id != id));
+        });
+    }
+
+    /// Handles every key while `owner` is on the focus path, or after the
+    /// focus path had its turn without one. Key releases are only reported
+    /// by terminals with keyboard enhancements and left out unless asked for.
+    pub(super) fn any_key(
+        &self,
+        owner: Option<usize>,
+        releases: bool,
+        handler: impl Fn(&KeyEvent) -> Propagation + 'static,
+    ) {
+        self.register(owner, Handler::Key(None, releases, Rc::new(handler)));
+    }
+
+    pub(super) fn remove_owned(&self, owner: usize) {
+        self.registry
+            .try_update_value(|r| r.entries.retain(|e| e.owner != Some(owner)));
+    }
+
+    // Handlers are collected up front, so ones registered while handling an
+    // event only see the next one
+    fn collect<T>(&self, f: impl Fn(&Entry) -> Option<T>) -> Vec<(Option<usize>, T)> {
+        self.registry.with_value(|r| {
+            r.entries
+                .iter()
+                .filter_map(|e| Some((e.owner, f(e)?)))
+                .collect()
+        })
+    }
+
+    /// Orders handlers from the focused component out, followed by the ones
+    /// outside of any focusable
+    fn bubble<T>(&self, handlers: Vec<(Option<usize>, T)>) -> Vec<T> {
+        let path = self.focus.path();
+        let mut handlers = handlers
+            .into_iter()
+            .filter_map(|(owner, h)| {
+                let depth = match owner {
+                    Some(o) => path.iter().position(|v| *v == o)?,
+                    None => path.len(),
+                };
+                Some((depth, h))
+            })
+            .collect::<Vec<_>>();
+
+        handlers.sort_by_key(|(depth, _)| *depth);
+        handlers.into_iter().map(|(_, h)| h).collect()
+    }
+
+    fn dispatch(&self, e: &Event) {
+        match e {
+            Event::Key(k) => {
+                let handlers = self.collect(|e| match &e.handler {
+                    Handler::Key(code, releases, h)
+                        if (*releases || k.kind != KeyEventKind::Release)
+                            && code.is_none_or(|c| c == k.code) =>
+                    {
+                        Some(h.clone())
+                    }
+                    _ => None,
+                });
+                let stopped = self
+                    .bubble(handlers)
+                    .into_iter()
+                    .any(|h| h(k) == Propagation::Stop);
+
+                if !stopped && k.kind != KeyEventKind::Release {
+                    match k.code {
+                        KeyCode::Tab => self.focus.step(1),
+                        KeyCode::BackTab => self.focus.step(-1),
+                        _ => (),
+                    }
+                }
+            }
+            Event::Paste(text) => {
+                let handlers = self.collect(|e| match &e.handler {
+                    Handler::Paste(h) => Some(h.clone()),
+                    _ => None,
+                });
+                let _ = self
+                    .bubble(handlers)
+                    .into_iter()
+                    .any(|h| h(text) == Propagation::Stop);
+            }
+            Event::FocusLost => {
+                let (column, row) = self.pointer();
+                self.release_capture(column, row);
+                self.hover(vec![])
+            }
+            Event::Mouse(m) => {
+                self.pointer.set_value((m.column, m.row));
+
+                let capture = self
+                    .capture
+                    .with_value(|c| c.current.as_ref().map(|(_, h)| h.clone()));
+                if let Some(capture) = capture {
+                    match m.kind {
+                        // A release outside of the terminal is never reported,
+                        // moving without a button held means it happened
+                        MouseEventKind::Moved | MouseEventKind::Down(_) => {
+                            self.release_capture(m.column, m.row)
+                        }
+                        MouseEventKind::Up(_) => {
+                            self.capture.update_value(|c| c.current = None);
+                            return capture(m);
+                        }
+                        _ => return capture(m),
+                    }
+                }
+
+                let topmost = self.hits(m.column, m.row).into_iter().next();
+                self.hover(topmost.unwrap_or_default());
+
+                if self.hit(m) == Propagation::Stop {
+                    return;
+                }
+
+                let handlers = self.collect(|e| match &e.handler {
+                    Handler::Mouse(area, h) => Some((area.get_untracked(), h.clone())),
+                    _ => None,
+                });
+                let hit = |a: &Rect| {
+                    a.left() <= m.column
+                        && m.column < a.right()
+                        && a.top() <= m.row
+                        && m.row < a.bottom()
+                };
+
+                // Whatever was registered last is assumed to be on top
+                let _ = handlers
+                    .into_iter()
+                    .rev()
+                    .filter(|(_, (area, _))| hit(area))
+                    .any(|(_, (_, h))| h(m) == Propagation::Stop);
+            }
+            Event::Resize(w, h) => {
+                let handlers = self.collect(|e| match &e.handler {
+                    Handler::Resize(h) => Some(h.clone()),
+                    _ => None,
+                });
+                for (_, handler) in handlers {
+                    handler(*w, *h)
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+impl EventHandlers {
+    fn hover(&self, ancestry: Vec<ScopeId>) {
+        if self.hovered.with_untracked(|v| *v != ancestry) {
+            self.hovered.set(ancestry)
+        }
+    }
+
+    /// The ancestries of the mounts at `(x, y)`, the topmost first
+    pub(super) fn hits(&self, x: u16, y: u16) -> Vec<Vec<ScopeId>> {
+        self.mounts.with_value(|mounts| {
+            mounts
+                .borrow()
+                .hit(x, y)
+                .into_iter()
+                .map(|v| v.ancestry)
+                .collect()
+        })
+    }
+
+    /// The position of the pointer as of the last mouse event
+    pub fn pointer(&self) -> (u16, u16) {
+        self.pointer.get_value()
+    }
+
+    /// Sends every mouse event to `handler` instead, until the button is
+    /// released or `cx` is disposed. Each call adds a cleanup to `cx`, so
+    /// repeated captures are better given a scope each.
+    pub fn capture(&self, cx: Scope, handler: impl Fn(&MouseEvent) + 'static) {
+        let id = self.capture_until_release(handler);
+
+        let v = *self;
+        on_cleanup(cx, move || v.release(id));
+    }
+
+    // Returns the id to `release` the capture with, in case it outlives its owner
+    pub(super) fn capture_until_release(&self, handler: impl Fn(&MouseEvent) + 'static) -> usize {
+        self.capture
+            .try_update_value(|c| {
+                let id = c.next;
+                c.next += 1;
+                c.current = Some((id, Rc::new(handler)));
+                id
+            })
+            .unwrap()
+    }
+
+    /// Ends the capture `id` without telling its handler, if it is still on
+    pub(super) fn release(&self, id: usize) {
+        self.capture.try_update_value(|c| {
+            if matches!(c.current, Some((v, _)) if v == id) {
+                c.current = None
+            }
+        });
+    }
+
+    // Ends the capture as if the button was released at `(column, row)`
+    fn release_capture(&self, column: u16, row: u16) {
+        let capture = self
+            .capture
+            .try_update_value(|c| c.current.take())
+            .flatten();
+
+        if let Some((_, capture)) = capture {
+            capture(&MouseEvent {
+                kind: MouseEventKind::Up(MouseButton::Left),
+                column,
+                row,
+                modifiers: KeyModifiers::NONE,
+            })
+        }
+    }
+
+    /// Hands `m` to the components under the pointer, the topmost first, in
+    /// coordinates local to the area that was hit
+    fn hit(&self, m: &MouseEvent) -> Propagation {
+        self.route(
+            (m.column, m.row),
+            |h| match h {
+                Handler::Pointer(owner, h) => Some((*owner, h.clone())),
+                _ => None,
+            },
+            |h, (column, row)| h(&MouseEvent { column, row, ..*m }),
+        )
+    }
+
+    /// Hands `g` to the components under it like a mouse event
+    pub(super) fn gesture(&self, g: Gesture) -> Propagation {
+        self.route(
+            g.position(),
+            |h| match h {
+                Handler::Gesture(owner, h) => Some((*owner, h.clone())),
+                _ => None,
+            },
+            |h, (column, row)| h(&g.at(column, row)),
+        )
+    }
+
+    // Calls the handlers `pick` finds for the scopes hit at `point`, and their
+    // ancestors, with the point local to each area
+    fn route<T>(
+        &self,
+        point: (u16, u16),
+        pick: impl Fn(&Handler) -> Option<(ScopeId, T)>,
+        call: impl Fn(&T, (u16, u16)) -> Propagation,
+    ) -> Propagation {
+        let hits = self
+            .mounts
+            .with_value(|mounts| mounts.borrow().hit(point.0, point.1));
+        let handlers = self.registry.with_value(|r| {
+            r.entries
+                .iter()
+                .filter_map(|e| pick(&e.handler).map(|(owner, h)| (e.id, owner, h)))
+                .collect::<Vec<_>>()
+        });
+
+        // Scopes sharing an ancestor would otherwise hand it the event twice
+        let mut called = HashSet::new();
+
+        for hit in hits {
+            let local = (hit.point.0 - hit.area.x, hit.point.1 - hit.area.y);
+
+            for scope in &hit.ancestry {
+                for (id, _, h) in handlers.iter().filter(|(_, o, _)| o == scope) {
+                    if called.insert(*id) && call(h, local) == Propagation::Stop {
+                        return Propagation::Stop;
+                    }
+                }
+            }
+        }
+
+        Propagation::Continue
+    }
+}
+
+fn handlers(cx: Scope) -> EventHandlers {
+    use_context(cx).unwrap()
+}
+
+/// Handles `code` while `cx` is focused, or at any time if `cx` is not
+/// within a focusable
+pub fn on_key(cx: Scope, code: KeyCode, handler: impl Fn(&KeyEvent) -> Propagation + 'static) {
+    handlers(cx).register_in(cx, Handler::Key(Some(code), false, Rc::new(handler)))
+}
+
+/// Handles mouse events within `area`, the most recently registered first
+pub fn on_mouse(
+    cx: Scope,
+    area: impl Into<MaybeSignal<Rect>>,
+    handler: impl Fn(&MouseEvent) -> Propagation + 'static,
+) {
+    handlers(cx).register_in(cx, Handler::Mouse(area.into(), Rc::new(handler)))
+}
+
+/// Handles mouse events over whatever `cx` rendered, unless something on
+/// top of it stops them. Positions are relative to the area that was hit.
+pub fn on_pointer(cx: Scope, handler: impl Fn(&MouseEvent) -> Propagation + 'static) {
+    handlers(cx).register_in(cx, Handler::Pointer(cx.id(), Rc::new(handler)))
+}
+
+/// Handles [`Gesture`]s over whatever `cx` rendered, routed and positioned
+/// like [`on_pointer`]
+pub fn on_gesture(cx: Scope, handler: impl Fn(&Gesture) -> Propagation + 'static) {
+    handlers(cx).register_in(cx, Handler::Gesture(cx.id(), Rc::new(handler)))
+}
+
+/// Handles pasted text, routed like keys
+pub fn on_paste(cx: Scope, handler: impl Fn(&str) -> Propagation + 'static) {
+    handlers(cx).register_in(cx, Handler::Paste(Rc::new(handler)))
+}
+
+/// Called with the new `(columns, rows)` of the terminal
+pub fn on_resize(cx: Scope, handler: impl Fn(u16, u16) + 'static) {
+    handlers(cx).register_in(cx, Handler::Resize(Rc::new(handler)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEventKind};
+    use leptos_reactive::*;
+    use tui::{layout::Rect, widgets::Paragraph};
+
+    use crate::bootstrapper::{
+        mouse,
+        shared_ctx::{use_focusable, EventDispatcher, RenderBaseAuto, RenderCounter},
+        test_bootstrap,
+    };
+
+    use super::{on_key, on_mouse, on_paste, on_pointer, on_resize, Propagation};
+
+    fn click(column: u16, row: u16) -> Event {
+        mouse(MouseEventKind::Down(MouseButton::Left), column, row)
+    }
+
+    #[test]
+    fn handlers_stop_and_clean_up() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let inner = Rc::clone(&log);
+
+        test_bootstrap(
+            move |cx| {
+                let ed = use_context::<EventDispatcher>(cx).unwrap();
+                let rc = use_context::<RenderCounter>(cx).unwrap();
+
+                let push = move |v: &'static str| {
+                    let log = Rc::clone(&inner);
+                    move || log.borrow_mut().push(v)
+                };
+
+                let global = push("global");
+                on_key(cx, KeyCode::Enter, move |_| {
+                    global();
+                    Propagation::Continue
+                });
+
+                let (_, child) = cx.run_child_scope(|cx| {
+                    use_focusable(cx).focus();
+
+                    let focused = push("focused");
+                    on_key(cx, KeyCode::Enter, move |_| {
+                        focused();
+                        Propagation::Stop
+                    });
+
+                    let lower = push("lower");
+                    on_mouse(cx, Rect::new(0, 0, 5, 5), move |_| {
+                        lower();
+                        Propagation::Continue
+                    });
+                    let upper = push("upper");
+                    on_mouse(cx, Rect::new(2, 2, 5, 5), move |_| {
+                        upper();
+                        Propagation::Continue
+                    });
+                });
+                let child = store_value(cx, Some(child));
+
+                let resized = push("resized");
+                on_resize(cx, move |_, _| resized());
+
+                let enter = Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+
+                create_effect(cx, move |_| match rc.0.get() {
+                    0 => {
+                        ed.dispatch(enter.clone()).unwrap();
+                        ed.dispatch(click(3, 3)).unwrap();
+                        ed.dispatch(click(1, 1)).unwrap();
+                        ed.dispatch(Event::Resize(10, 10)).unwrap();
+                    }
+                    4 => {
+                        child.update_value(|v| v.take().unwrap().dispose());
+                        ed.dispatch(enter.clone()).unwrap();
+                    }
+                    _ => (),
+                });
+            },
+            10,
+            10,
+            Some(6),
+        )
+        .unwrap();
+
+        assert_eq!(
+            *log.borrow(),
+            ["focused", "upper", "lower", "lower", "resized", "global"]
+        );
+    }
+
+    #[test]
+    fn signals_read_by_handlers_do_not_repeat_events() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let inner = Rc::clone(&log);
+
+        test_bootstrap(
+            move |cx| {
+                let ed = use_context::<EventDispatcher>(cx).unwrap();
+                let rc = use_context::<RenderCounter>(cx).unwrap();
+                let count = create_rw_signal(cx, 0);
+
+                on_key(cx, KeyCode::Enter, move |_| {
+                    inner.borrow_mut().push(count.get());
+                    Propagation::Continue
+                });
+
+                let enter = Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+                create_effect(cx, move |_| match rc.0.get() {
+                    0 => ed.dispatch(enter.clone()).unwrap(),
+                    2 => count.set(1),
+                    _ => (),
+                });
+            },
+            10,
+            10,
+            Some(4),
+        )
+        .unwrap();
+
+        assert_eq!(*log.borrow(), [0]);
+    }
+
+    #[test]
+    fn pointer_hits_topmost() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let inner = Rc::clone(&log);
+
+        test_bootstrap(
+            move |cx| {
+                let ed = use_context::<EventDispatcher>(cx).unwrap();
+                let rc = use_context::<RenderCounter>(cx).unwrap();
+                let rb: RenderBaseAuto = use_context(cx).unwrap();
+
+                let mount = move |name: &'static str, layer, area, stop| {
+                    let rb = rb.layer(layer);
+                    let log = Rc::clone(&inner);
+
+                    let _ = cx.run_child_scope(move |cx| {
+                        rb.render(cx, Paragraph::new(name), area);
+
+                        on_pointer(cx, move |m| {
+                            log.borrow_mut().push((name, m.column, m.row));
+                            stop
+                        });
+                    });
+                };
+
+                mount("upper", 1, Rect::new(2, 2, 4, 4), Propagation::Continue);
+                mount("under", 0, Rect::new(0, 0, 8, 8), Propagation::Continue);
+                mount("lower", 0, Rect::new(0, 0, 4, 4), Propagation::Stop);
+
+                create_effect(cx, move |_| {
+                    if rc.0.get() == 0 {
+                        ed.dispatch(click(3, 3)).unwrap();
+                        ed.dispatch(click(7, 7)).unwrap();
+                    }
+                });
+            },
+            10,
+            10,
+            Some(3),
+        )
+        .unwrap();
+
+        assert_eq!(
+            *log.borrow(),
+            [("upper", 1, 1), ("lower", 3, 3), ("under", 7, 7)]
+        );
+    }
+
+    #[test]
+    fn pointer_reaches_into_scroll_views() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let inner = Rc::clone(&log);
+
+        test_bootstrap(
+            move |cx| {
+                let ed = use_context::<EventDispatcher>(cx).unwrap();
+                let rc = use_context::<RenderCounter>(cx).unwrap();
+                let rb: RenderBaseAuto = use_context(cx).unwrap();
+
+                let area = Signal::derive(cx, || Rect::new(2, 1, 6, 2));
+                let size = Signal::derive(cx, || (10, 2));
+                let offset = Signal::derive(cx, || (3, 0));
+
+                let log = Rc::clone(&inner);
+                rb.scroll_view(cx, area, size, offset, move |cx| {
+                    let rb: RenderBaseAuto = use_context(cx).unwrap();
+                    rb.render(cx, Paragraph::new("inner"), Rect::new(4, 0, 3, 1));
+
+                    on_pointer(cx, move |m| {
+                        log.borrow_mut().push(("inner", m.column, m.row));
+                        Propagation::Stop
+                    });
+                });
+
+                create_effect(cx, move |_| {
+                    if rc.0.get() == 0 {
+                        // Scrolled into the child, then next to it
+                        ed.dispatch(click(4, 1)).unwrap();
+                        ed.dispatch(click(2, 1)).unwrap();
+                    }
+                });
+            },
+            10,
+            4,
+            Some(3),
+        )
+        .unwrap();
+
+        assert_eq!(*log.borrow(), [("inner", 1, 0)]);
+    }
+
+    #[test]
+    fn paste_arrives_whole_at_focus() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let inner = Rc::clone(&log);
+
+        test_bootstrap(
+            move |cx| {
+                let ed = use_context::<EventDispatcher>(cx).unwrap();
+
+                let log = Rc::clone(&inner);
+                on_key(cx, KeyCode::Enter, move |k| {
+                    log.borrow_mut().push(format!("key {:?}", k.code));
+                    Propagation::Continue
+                });
+
+                for name in ["a", "b"] {
+                    let (f, _) = cx.run_child_scope(|cx| {
+                        let f = use_focusable(cx);
+                        let log = Rc::clone(&inner);
+                        on_paste(cx, move |text| {
+                            log.borrow_mut().push(format!("{} {:?}", name, text));
+                            Propagation::Stop
+                        });
+                        f
+                    });
+
+                    if name == "b" {
+                        f.focus();
+                    }
+                }
+
+                ed.dispatch(Event::Paste("one\ntwo".into())).unwrap();
+            },
+            10,
+            10,
+            Some(2),
+        )
+        .unwrap();
+
+        assert_eq!(*log.borrow(), ["b \"one\\ntwo\""]);
+    }
+}
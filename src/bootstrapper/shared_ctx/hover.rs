@@ -0,0 +1,122 @@
+use std::{cell::Cell, rc::Rc};
+
+use leptos_reactive::*;
+
+use super::EventHandlers;
+
+/// Whether the pointer is over anything `cx` or its descendants rendered,
+/// without something else on top of it
+pub fn use_hovered(cx: Scope) -> Memo<bool> {
+    let hovered = use_context::<EventHandlers>(cx).unwrap().hovered;
+    let id = cx.id();
+
+    create_memo(cx, move |_| hovered.with(|v| v.contains(&id)))
+}
+
+/// Called when the pointer moves onto `cx`
+pub fn on_mouse_enter(cx: Scope, f: impl Fn() + 'static) {
+    let hovered = use_hovered(cx);
+
+    create_effect(cx, move |prev: Option<bool>| {
+        let now = hovered.get();
+        if now && !prev.unwrap_or(false) {
+            f()
+        }
+        now
+    });
+}
+
+/// Called when the pointer moves off `cx`, leaves the terminal, or `cx` is
+/// disposed while hovered. Terminals only tell that the pointer left by
+/// losing focus, so without [`TerminalModes::focus_change`](crate::bootstrapper::TerminalModes)
+/// this waits until it moves onto something else.
+pub fn on_mouse_leave(cx: Scope, f: impl Fn() + 'static) {
+    let hovered = use_hovered(cx);
+    let f = Rc::new(f);
+    let last = Rc::new(Cell::new(false));
+
+    {
+        let (f, last) = (Rc::clone(&f), Rc::clone(&last));
+        create_effect(cx, move |_| {
+            let now = hovered.get();
+            if last.replace(now) && !now {
+                f()
+            }
+        });
+    }
+
+    on_cleanup(cx, move || {
+        if last.get() {
+            f()
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use crossterm::event::{Event, MouseEventKind};
+    use leptos_reactive::*;
+    use tui::{layout::Rect, widgets::Paragraph};
+
+    use crate::bootstrapper::{
+        mouse,
+        shared_ctx::{EventDispatcher, RenderBaseAuto, RenderCounter},
+        test_bootstrap,
+    };
+
+    use super::{on_mouse_enter, on_mouse_leave};
+
+    #[test]
+    fn enter_and_leave() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let inner = Rc::clone(&log);
+
+        test_bootstrap(
+            move |cx| {
+                let ed = use_context::<EventDispatcher>(cx).unwrap();
+                let rc = use_context::<RenderCounter>(cx).unwrap();
+                let rb: RenderBaseAuto = use_context(cx).unwrap();
+
+                let (_, child) = cx.run_child_scope(move |cx| {
+                    rb.render(cx, Paragraph::new("hover"), Rect::new(0, 0, 5, 5));
+
+                    let log = Rc::clone(&inner);
+                    on_mouse_enter(cx, move || log.borrow_mut().push("enter"));
+                    let log = Rc::clone(&inner);
+                    on_mouse_leave(cx, move || log.borrow_mut().push("leave"));
+                });
+                let child = store_value(cx, Some(child));
+
+                let moved = |column, row| mouse(MouseEventKind::Moved, column, row);
+
+                create_effect(cx, move |_| match rc.0.get() {
+                    0 => {
+                        for e in [
+                            moved(1, 1),
+                            moved(2, 2),
+                            moved(7, 7),
+                            moved(1, 1),
+                            Event::FocusLost,
+                            moved(1, 1),
+                        ] {
+                            ed.dispatch(e).unwrap();
+                        }
+                    }
+                    6 => child.update_value(|v| v.take().unwrap().dispose()),
+                    _ => (),
+                });
+            },
+            10,
+            10,
+            Some(7),
+        )
+        .unwrap();
+
+        assert_eq!(
+            *log.borrow(),
+            ["enter", "leave", "enter", "leave", "enter", "leave"]
+        );
+    }
+}
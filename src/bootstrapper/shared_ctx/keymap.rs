@@ -0,0 +1,668 @@
+use std::{error::Error, fmt::Display, rc::Rc, time::Duration};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use leptos_reactive::*;
+use tui::{
+    backend::Backend,
+    layout::Rect,
+    text::{Span, Spans, Text},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+use crate::controlflow::show;
+
+use super::{
+    Clock, Console, EventHandlers, FocusManager, Focusable, Layer, Propagation, Region, RenderBase,
+    RenderCounter,
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct KeymapConfig {
+    /// What `<leader>` stands for in a key sequence
+    pub leader: KeyCode,
+    /// How long an ambiguous sequence waits for its next key
+    pub timeout: Duration,
+    /// Shows the possible next keys while a sequence is pending
+    pub which_key: bool,
+}
+
+impl Default for KeymapConfig {
+    fn default() -> Self {
+        Self {
+            leader: KeyCode::Char(' '),
+            timeout: Duration::from_millis(1000),
+            which_key: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct KeyParseError(pub String);
+
+impl Display for KeyParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid key sequence: {}", self.0)
+    }
+}
+
+impl Error for KeyParseError {}
+
+/// A single key within a sequence
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Chord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+    /// Releases are written with a `Release-` prefix and only reported
+    /// with keyboard enhancements, repeats count as presses
+    pub kind: KeyEventKind,
+}
+
+impl From<&KeyEvent> for Chord {
+    fn from(k: &KeyEvent) -> Self {
+        // Shift is already part of the character
+        let modifiers = match k.code {
+            KeyCode::Char(_) => k.modifiers - KeyModifiers::SHIFT,
+            _ => k.modifiers,
+        };
+
+        let kind = match k.kind {
+            KeyEventKind::Release => KeyEventKind::Release,
+            _ => KeyEventKind::Press,
+        };
+
+        Self {
+            code: k.code,
+            modifiers,
+            kind,
+        }
+    }
+}
+
+impl Chord {
+    fn parse(token: &str, leader: KeyCode) -> Result<Self, KeyParseError> {
+        let err = || KeyParseError(token.into());
+
+        let token = match token.strip_prefix('<').and_then(|t| t.strip_suffix('>')) {
+            Some(t) if t.eq_ignore_ascii_case("leader") => {
+                return Ok(Self {
+                    code: leader,
+                    modifiers: KeyModifiers::NONE,
+                    kind: KeyEventKind::Press,
+                })
+            }
+            Some(t) => t,
+            None => token,
+        };
+
+        // The last part is the key, so `Ctrl--` is a minus
+        let (mods, key) = match token.len() {
+            1 => ("", token),
+            _ => match token.rsplit_once('-') {
+                Some((m, "")) => (m.strip_suffix('-').ok_or_else(err)?, "-"),
+                Some((m, k)) => (m, k),
+                None => ("", token),
+            },
+        };
+
+        let (mut modifiers, mut kind) = (KeyModifiers::NONE, KeyEventKind::Press);
+        for m in mods.split('-').filter(|v| !v.is_empty()) {
+            modifiers |= match m.to_lowercase().as_str() {
+                "release" => {
+                    kind = KeyEventKind::Release;
+                    continue;
+                }
+                "ctrl" | "c" => KeyModifiers::CONTROL,
+                "alt" | "a" | "m" | "meta" => KeyModifiers::ALT,
+                "shift" | "s" => KeyModifiers::SHIFT,
+                _ => return Err(err()),
+            };
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            // Shift is part of the character, as with key events
+            (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+                modifiers -= KeyModifiers::SHIFT;
+                KeyCode::Char(c.to_uppercase().next().unwrap_or(c))
+            }
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_lowercase().as_str() {
+                "enter" | "cr" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "space" => KeyCode::Char(' '),
+                "bs" | "backspace" => KeyCode::Backspace,
+                "del" | "delete" => KeyCode::Delete,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "insert" => KeyCode::Insert,
+                f => match f.strip_prefix('f').map(str::parse) {
+                    Some(Ok(n)) => KeyCode::F(n),
+                    _ => return Err(err()),
+                },
+            },
+        };
+
+        Ok(Self {
+            code,
+            modifiers,
+            kind,
+        })
+    }
+}
+
+impl Display for Chord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (m, name) in [
+            (KeyModifiers::CONTROL, "Ctrl-"),
+            (KeyModifiers::ALT, "Alt-"),
+            (KeyModifiers::SHIFT, "Shift-"),
+        ] {
+            if self.modifiers.contains(m) {
+                write!(f, "{}", name)?;
+            }
+        }
+        if self.kind == KeyEventKind::Release {
+            write!(f, "Release-")?;
+        }
+
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            c => write!(f, "{:?}", c),
+        }
+    }
+}
+
+/// Keys pressed one after another, written like `g g`, `Ctrl-x Ctrl-s` or
+/// `<leader>f`
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct KeySeq(pub Vec<Chord>);
+
+impl KeySeq {
+    pub fn parse(s: &str, leader: KeyCode) -> Result<Self, KeyParseError> {
+        let mut chords = vec![];
+
+        for token in s.split_whitespace() {
+            // `<leader>f` is two keys without a space between them
+            match token.strip_prefix("<leader>") {
+                Some(rest) if !rest.is_empty() => {
+                    chords.push(Chord::parse("<leader>", leader)?);
+                    chords.push(Chord::parse(rest, leader)?);
+                }
+                _ => chords.push(Chord::parse(token, leader)?),
+            }
+        }
+
+        if chords.is_empty() {
+            return Err(KeyParseError(s.into()));
+        }
+
+        Ok(Self(chords))
+    }
+}
+
+impl Display for KeySeq {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut first = true;
+        for chord in &self.0 {
+            if !first {
+                write!(f, " ")?;
+            }
+            first = false;
+
+            write!(f, "{}", chord)?;
+        }
+        Ok(())
+    }
+}
+
+// A sequence, its description and its action
+type Active = (KeySeq, String, Rc<dyn Fn()>);
+
+struct Binding {
+    id: usize,
+    // The focusable the binding is scoped to, like key handlers
+    owner: Option<usize>,
+    mode: String,
+    seq: KeySeq,
+    description: String,
+    action: Rc<dyn Fn()>,
+}
+
+#[derive(Default)]
+struct Bindings {
+    next: usize,
+    list: Vec<Binding>,
+    since: Duration,
+}
+
+/// Key sequences bound to actions per mode
+#[derive(Clone, Copy)]
+pub struct Keymap {
+    config: KeymapConfig,
+    mode: RwSignal<String>,
+    pending: RwSignal<Vec<Chord>>,
+    bindings: StoredValue<Bindings>,
+    focus: FocusManager,
+}
+
+impl Keymap {
+    pub fn attach<B: Backend + 'static>(cx: Scope, config: KeymapConfig) -> Self {
+        let v = Self {
+            config,
+            mode: create_rw_signal(cx, "normal".into()),
+            pending: create_rw_signal(cx, vec![]),
+            bindings: store_value(cx, Default::default()),
+            focus: use_context(cx).unwrap(),
+        };
+        provide_context(cx, v);
+
+        // After the focus path, so focused components see keys first
+        let handlers = use_context::<EventHandlers>(cx).unwrap();
+        let clock = use_context::<Clock>(cx).unwrap();
+        {
+            let clock = clock.clone();
+            handlers.any_key(None, true, move |k| v.key(k, clock.now()));
+        }
+
+        let rc: RenderCounter = use_context(cx).unwrap();
+        create_effect(cx, move |_| {
+            rc.0.get();
+            v.expire(clock.now());
+        });
+
+        if config.which_key {
+            v.which_key::<B>(cx);
+        }
+
+        v
+    }
+
+    pub fn mode(&self) -> Signal<String> {
+        self.mode.into()
+    }
+
+    pub fn set_mode(&self, mode: impl Into<String>) {
+        self.pending.set(vec![]);
+        self.mode.set(mode.into())
+    }
+
+    /// The keys of the sequence in progress
+    pub fn pending(&self) -> Signal<Vec<Chord>> {
+        self.pending.into()
+    }
+
+    pub fn parse(&self, seq: &str) -> Result<KeySeq, KeyParseError> {
+        KeySeq::parse(seq, self.config.leader)
+    }
+
+    /// The bindings active in the current mode, the most deeply focused first
+    fn active(&self) -> Vec<Active> {
+        let path = self.focus.path();
+        let mode = self.mode.get_untracked();
+
+        self.bindings.with_value(|b| {
+            let mut active = b
+                .list
+                .iter()
+                .filter(|v| v.mode == mode)
+                .filter_map(|v| {
+                    let depth = match v.owner {
+                        Some(o) => path.iter().position(|p| *p == o)?,
+                        None => path.len(),
+                    };
+                    Some((depth, v))
+                })
+                .collect::<Vec<_>>();
+            active.sort_by_key(|(depth, _)| *depth);
+
+            active
+                .into_iter()
+                .map(|(_, v)| (v.seq.clone(), v.description.clone(), Rc::clone(&v.action)))
+                .collect()
+        })
+    }
+
+    fn key(&self, k: &KeyEvent, now: Duration) -> Propagation {
+        let pending = self.pending.get_untracked();
+        let mut seq = pending.clone();
+        seq.push(Chord::from(k));
+
+        let active = self.active();
+        let exact = active.iter().find(|(s, ..)| s.0 == seq);
+        let longer = active
+            .iter()
+            .any(|(s, ..)| s.0.len() > seq.len() && s.0.starts_with(&seq));
+
+        if longer {
+            // An exact match still has to wait for the timeout
+            self.bindings.update_value(|b| b.since = now);
+            self.pending.set(seq);
+            return Propagation::Stop;
+        }
+
+        // Releases nothing is bound to are not keys the sequence waits for
+        if exact.is_none() && k.kind == KeyEventKind::Release {
+            return Propagation::Continue;
+        }
+
+        if !pending.is_empty() {
+            self.pending.set(vec![]);
+        }
+
+        match exact {
+            Some((.., action)) => {
+                action();
+                Propagation::Stop
+            }
+            // A broken sequence runs what it matched so far, then starts over
+            // from the key that broke it
+            None if !pending.is_empty() => {
+                if let Some((.., action)) = active.iter().find(|(s, ..)| s.0 == pending) {
+                    action()
+                }
+                self.key(k, now)
+            }
+            None => Propagation::Continue,
+        }
+    }
+
+    /// Runs the binding of a sequence that waited too long for its next key
+    fn expire(&self, now: Duration) {
+        let since = self.bindings.with_value(|b| b.since);
+        if self.pending.with_untracked(Vec::is_empty)
+            || now.saturating_sub(since) < self.config.timeout
+        {
+            return;
+        }
+
+        let seq = self.pending.get_untracked();
+        self.pending.set(vec![]);
+
+        if let Some((.., action)) = self.active().into_iter().find(|(s, ..)| s.0 == seq) {
+            action()
+        }
+    }
+
+    fn which_key<B: Backend + 'static>(&self, cx: Scope) {
+        let v = *self;
+
+        show(
+            cx,
+            move || v.pending.with(|p| !p.is_empty()),
+            move |cx| {
+                let rb = use_context::<RenderBase<B>>(cx)
+                    .unwrap()
+                    .layer(Layer::MAX - 2);
+                let region: Region = use_context(cx).unwrap();
+
+                create_effect(cx, move |_| {
+                    let pending = v.pending.get();
+                    let mut lines = v
+                        .active()
+                        .into_iter()
+                        .filter(|(s, ..)| s.0.len() > pending.len() && s.0.starts_with(&pending))
+                        .map(|(s, description, _)| {
+                            let rest = KeySeq(s.0[pending.len()..].to_vec());
+                            Spans::from(vec![
+                                Span::raw(format!("{:>8} ", rest)),
+                                Span::raw(description),
+                            ])
+                        })
+                        .collect::<Vec<_>>();
+                    lines.dedup();
+
+                    let r = region();
+                    let h = std::cmp::min(lines.len() as u16 + 2, r.height / 2);
+                    let area = Rect::new(r.x, r.bottom() - h, r.width, h);
+
+                    rb.render(cx, Clear, area);
+                    rb.render(
+                        cx,
+                        Paragraph::new(Text { lines }).block(
+                            Block::default()
+                                .borders(Borders::ALL)
+                                .title(KeySeq(pending).to_string()),
+                        ),
+                        area,
+                    );
+                });
+            },
+            |_cx| {},
+        );
+    }
+}
+
+/// Binds `seq` in `mode` to `action`, while `cx` is focused or at any time
+/// if it is not within a focusable. Sequences bound twice in the same place
+/// are reported on the console.
+pub fn bind(
+    cx: Scope,
+    mode: &str,
+    seq: &str,
+    description: impl Into<String>,
+    action: impl Fn() + 'static,
+) -> Result<(), KeyParseError> {
+    let keymap = use_context::<Keymap>(cx).unwrap();
+    let owner = use_context::<Focusable>(cx).map(|f| f.id());
+    let seq = keymap.parse(seq)?;
+    let description = description.into();
+
+    let conflict = keymap.bindings.with_value(|b| {
+        b.list
+            .iter()
+            .find(|v| v.owner == owner && v.mode == mode && v.seq == seq)
+            .map(|v| v.description.clone())
+    });
+    if let (Some(other), Some(console)) = (conflict, use_context::<Console>(cx)) {
+        console.log(format!(
+            "Key conflict: `{}` in {} is bound to both \"{}\" and \"{}\"",
+            seq, mode, other, description
+        ));
+    }
+
+    let id = keymap
+        .bindings
+        .try_update_value(|b| {
+            let id = b.next;
+            b.next += 1;
+            b.list.push(Binding {
+                id,
+                owner,
+                mode: mode.into(),
+                seq,
+                description,
+                action: Rc::new(action),
+            });
+            id
+        })
+        .unwrap();
+
+    on_cleanup(cx, move || {
+        keymap
+            .bindings
+            .try_update_value(|b| b.list.retain(|v| v.id != id));
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc, time::Duration};
+
+    use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+    use leptos_reactive::*;
+
+    use crate::bootstrapper::{
+        shared_ctx::{Clock, Console, EventDispatcher, RenderCounter},
+        test_bootstrap,
+    };
+
+    use super::{bind, Chord, KeySeq, Keymap};
+
+    #[test]
+    fn parses_sequences() {
+        let seq = KeySeq::parse(
+            "<leader>f Ctrl-x <CR> F5 Ctrl-- Release-Tab",
+            KeyCode::Char(' '),
+        )
+        .unwrap();
+        let chord = |code, modifiers| Chord {
+            code,
+            modifiers,
+            kind: KeyEventKind::Press,
+        };
+
+        assert_eq!(
+            seq.0,
+            [
+                chord(KeyCode::Char(' '), KeyModifiers::NONE),
+                chord(KeyCode::Char('f'), KeyModifiers::NONE),
+                chord(KeyCode::Char('x'), KeyModifiers::CONTROL),
+                chord(KeyCode::Enter, KeyModifiers::NONE),
+                chord(KeyCode::F(5), KeyModifiers::NONE),
+                chord(KeyCode::Char('-'), KeyModifiers::CONTROL),
+                Chord {
+                    kind: KeyEventKind::Release,
+                    ..chord(KeyCode::Tab, KeyModifiers::NONE)
+                },
+            ]
+        );
+        assert_eq!(
+            seq.to_string(),
+            "Space f Ctrl-x Enter F5 Ctrl-- Release-Tab"
+        );
+        assert!(KeySeq::parse("Hyper-x", KeyCode::Char(' ')).is_err());
+
+        let shifted = KeySeq::parse("Shift-a Ctrl-Shift-b", KeyCode::Char(' ')).unwrap();
+        assert_eq!(
+            shifted.0,
+            [
+                Chord::from(&KeyEvent::new(KeyCode::Char('A'), KeyModifiers::SHIFT)),
+                Chord::from(&KeyEvent::new(
+                    KeyCode::Char('B'),
+                    KeyModifiers::CONTROL | KeyModifiers::SHIFT
+                )),
+            ]
+        );
+    }
+
+    #[test]
+    fn chords_modes_and_timeout() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let inner = Rc::clone(&log);
+
+        test_bootstrap(
+            move |cx| {
+                let console = Console::attach(cx);
+                let ed = use_context::<EventDispatcher>(cx).unwrap();
+                let rc = use_context::<RenderCounter>(cx).unwrap();
+                let clock = use_context::<Clock>(cx).unwrap();
+                let keymap = use_context::<Keymap>(cx).unwrap();
+
+                for (mode, seq, name) in [
+                    ("normal", "g", "top"),
+                    ("normal", "g g", "first line"),
+                    ("normal", "Ctrl-x Ctrl-s", "save"),
+                    ("normal", "<leader>f", "find"),
+                    ("normal", "Release-x", "released"),
+                    ("insert", "<Esc>", "leave insert"),
+                ] {
+                    let log = Rc::clone(&inner);
+                    bind(cx, mode, seq, name, move || {
+                        log.borrow_mut().push(name);
+                        if name == "leave insert" {
+                            keymap.set_mode("normal");
+                        }
+                    })
+                    .unwrap();
+                }
+
+                bind(cx, "normal", "g", "again", || ()).unwrap();
+                let conflict = console.with_untracked(|v| v.last().unwrap().1.clone());
+                assert!(conflict.starts_with("Key conflict: `g` in normal"));
+
+                let key = |code, modifiers| Event::Key(KeyEvent::new(code, modifiers));
+                let char = move |c| key(KeyCode::Char(c), KeyModifiers::NONE);
+                let ctrl = move |c| key(KeyCode::Char(c), KeyModifiers::CONTROL);
+                let release = |c| {
+                    Event::Key(KeyEvent::new_with_kind(
+                        KeyCode::Char(c),
+                        KeyModifiers::NONE,
+                        KeyEventKind::Release,
+                    ))
+                };
+
+                create_effect(cx, move |_| match rc.0.get() {
+                    0 => {
+                        for e in [
+                            char('g'),
+                            char('g'),
+                            ctrl('x'),
+                            ctrl('s'),
+                            char(' '),
+                            // Releases do not break a sequence
+                            release(' '),
+                            char('f'),
+                            release('x'),
+                            char('g'),
+                        ] {
+                            ed.dispatch(e).unwrap();
+                        }
+                    }
+                    9 => {
+                        // `g` waits for a possible `g g`
+                        assert_eq!(
+                            keymap.pending().get_untracked(),
+                            [Chord::from(&KeyEvent::new(
+                                KeyCode::Char('g'),
+                                KeyModifiers::NONE
+                            ))]
+                        );
+                        clock.advance(Duration::from_secs(1));
+                    }
+                    10 => {
+                        keymap.set_mode("insert");
+                        ed.dispatch(char('g')).unwrap();
+                        ed.dispatch(key(KeyCode::Esc, KeyModifiers::NONE)).unwrap();
+                    }
+                    12 => {
+                        assert_eq!(keymap.mode().get_untracked(), "normal");
+
+                        // Broken by a key that continues nothing
+                        ed.dispatch(char('g')).unwrap();
+                        ed.dispatch(char('x')).unwrap();
+                    }
+                    _ => (),
+                });
+            },
+            10,
+            10,
+            Some(15),
+        )
+        .unwrap();
+
+        assert_eq!(
+            *log.borrow(),
+            [
+                "first line",
+                "save",
+                "find",
+                "released",
+                "top",
+                "leave insert",
+                "top"
+            ]
+        );
+    }
+}
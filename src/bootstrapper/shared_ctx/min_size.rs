@@ -0,0 +1,50 @@
+use leptos_reactive::*;
+use tui::{
+    backend::Backend,
+    layout::{Alignment, Rect},
+    widgets::{Clear, Paragraph, Wrap},
+};
+
+use crate::controlflow::show;
+
+use super::{Layer, Region, RenderBase};
+
+/// Covers the app with a notice while the [`Region`] is smaller than `min`,
+/// the app itself stays mounted underneath
+pub fn min_size_guard<B: Backend + 'static>(cx: Scope, min: (u16, u16)) {
+    let region: Region = use_context(cx).unwrap();
+
+    let too_small = move || {
+        let r = region();
+        r.width < min.0 || r.height < min.1
+    };
+
+    show(
+        cx,
+        too_small,
+        move |cx| {
+            let rb = use_context::<RenderBase<B>>(cx).unwrap().layer(Layer::MAX);
+
+            create_effect(cx, move |_| {
+                let r = region();
+                let text = format!(
+                    "Terminal too small\n{}x{}, needs {}x{}",
+                    r.width, r.height, min.0, min.1
+                );
+
+                let y = r.y + r.height.saturating_sub(2) / 2;
+                let notice = Rect::new(r.x, y, r.width, std::cmp::min(2, r.height));
+
+                rb.render(cx, Clear, r);
+                rb.render(
+                    cx,
+                    Paragraph::new(text)
+                        .alignment(Alignment::Center)
+                        .wrap(Wrap { trim: true }),
+                    notice,
+                );
+            });
+        },
+        |_cx| {},
+    );
+}
@@ -0,0 +1,168 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use leptos_reactive::*;
+use tui::{backend::Backend, buffer::Buffer, layout::Rect, widgets::Widget};
+
+use super::{
+    rb::{blit, translate},
+    Region, RenderBase,
+};
+
+pub(super) struct Offscreen {
+    pub(super) buffer: RefCell<Buffer>,
+    pub(super) dirty: RwSignal<()>,
+
+    // Set while the owner flushes, renders caused by the flush need no new one
+    pub(super) flushing: Cell<bool>,
+}
+
+/// Copies a buffer into the frame, `offset` being the position within the
+/// buffer shown at the top left of the area
+struct Blit {
+    buffer: Buffer,
+    offset: (u16, u16),
+}
+
+impl Widget for Blit {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if !area.intersects(buf.area) {
+            return;
+        }
+
+        let shown = translate(
+            Rect::new(
+                self.buffer.area.x,
+                self.buffer.area.y,
+                area.width,
+                area.height,
+            ),
+            self.offset.0,
+            self.offset.1,
+        );
+
+        // Anything outside of the buffer is shown blank
+        let mut scratch = Buffer::empty(shown);
+        if shown.intersects(self.buffer.area) {
+            blit(
+                &self.buffer,
+                &mut scratch,
+                shown.intersection(self.buffer.area),
+            );
+        }
+
+        // Moves the scratch buffer onto the frame
+        scratch.area.x = area.x;
+        scratch.area.y = area.y;
+
+        blit(&scratch, buf, area.intersection(buf.area));
+    }
+}
+
+impl<B: Backend + 'static> RenderBase<B> {
+    /// Renders `child` into its own buffer that is blit into `area`. The
+    /// child only re-renders when its own dependencies change, damage to
+    /// `area` is repaired from the cached buffer.
+    pub fn offscreen(&self, cx: Scope, area: Signal<Rect>, child: impl FnOnce(Scope)) {
+        let size = Signal::derive(cx, move || area.with(|a| (a.width, a.height)));
+
+        self.scroll_view(cx, area, size, Signal::derive(cx, || (0, 0)), child)
+    }
+
+    /// Renders `child` into a `size` buffer of its own and shows the part of
+    /// it starting at `offset` in `area`
+    pub fn scroll_view(
+        &self,
+        cx: Scope,
+        area: Signal<Rect>,
+        size: Signal<(u16, u16)>,
+        offset: Signal<(u16, u16)>,
+        child: impl FnOnce(Scope),
+    ) {
+        let canvas = create_memo(cx, move |_| {
+            let (w, h) = size();
+            Rect::new(0, 0, w, h)
+        });
+
+        let offscreen = Rc::new(Offscreen {
+            buffer: RefCell::new(Buffer::empty(canvas.get_untracked())),
+            dirty: create_rw_signal(cx, ()),
+            flushing: Cell::new(false),
+        });
+
+        let inner = Self {
+            mounts: Default::default(),
+            commands: Default::default(),
+            layer: 0,
+            clip: None,
+            offscreen: Some(Rc::clone(&offscreen)),
+            ..self.clone()
+        };
+
+        {
+            let inner = inner.clone();
+            cx.child_scope(move |cx| {
+                Region::derive(cx, canvas.into());
+                provide_context(cx, inner);
+
+                child(cx);
+            });
+        }
+
+        {
+            let offscreen = Rc::clone(&offscreen);
+            let inner = inner.clone();
+            create_effect(cx, move |_| {
+                let canvas = canvas();
+
+                {
+                    let mut buffer = offscreen.buffer.borrow_mut();
+                    if buffer.area == canvas {
+                        return;
+                    }
+
+                    buffer.resize(canvas);
+                    buffer.reset();
+                }
+
+                let all = inner.mounts.borrow().all();
+                for redraw in all {
+                    redraw.set(());
+                }
+            });
+        }
+
+        // The pointer reaches the child's mounts through the blit
+        let mounts = Rc::downgrade(&self.mounts);
+        on_cleanup(cx, move || {
+            if let Some(mounts) = mounts.upgrade() {
+                mounts.borrow_mut().unnest(cx.id());
+            }
+        });
+
+        let parent = self.clone();
+        create_effect(cx, move |_| {
+            offscreen.dirty.get();
+
+            offscreen.flushing.set(true);
+            inner.unmount();
+            inner.flush();
+            offscreen.flushing.set(false);
+
+            let (area, offset) = (area(), offset());
+            let view = match parent.clip {
+                Some(region) => region.with(|r| translate(area, r.x, r.y)),
+                None => area,
+            };
+            parent
+                .mounts
+                .borrow_mut()
+                .nest(cx.id(), Rc::clone(&inner.mounts), view, offset);
+
+            let buffer = offscreen.buffer.borrow().clone();
+            parent.render(cx, Blit { buffer, offset }, area);
+        });
+    }
+}
@@ -1,25 +1,272 @@
+use std::{cell::Cell, rc::Rc};
+
 use leptos_reactive::*;
 
-#[derive(Clone, Copy)]
-pub struct Quit(RwSignal<Option<String>>);
+use super::TermSignal;
+
+/// What ended the main-loop
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Stopped<T> {
+    /// Through [`Quit<T>`], with the app's result
+    Quit(T),
+    /// Through [`QuitAny`], which has no result to give
+    Dismissed,
+    /// By a signal, after the [`on_shutdown`](super::on_shutdown) hooks ran
+    Signal(TermSignal),
+}
+
+impl<T> Stopped<T> {
+    pub fn value(self) -> Option<T> {
+        match self {
+            Self::Quit(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+/// Ends the main-loop, handing `T` back to whoever bootstrapped the app
+pub struct Quit<T: 'static = String>(StoredValue<Option<T>>, QuitAny);
 
-impl Quit {
+impl<T: 'static> Clone for Quit<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T: 'static> Copy for Quit<T> {}
+
+impl<T: 'static> Quit<T> {
     pub fn attach(cx: Scope) -> Self {
-        let v = Self(create_rw_signal(cx, None));
+        let v = Self(store_value(cx, None), QuitAny::attach(cx));
         provide_context(cx, v);
         v
     }
 
+    /// Quits once every [`on_before_quit`] handler allows it
+    pub fn quit_with(&self, value: T) {
+        let (out, stop) = (self.0, self.1.stop);
+        let value = Cell::new(Some(value));
+
+        self.1.guards.request(Rc::new(move || {
+            if let Some(v) = value.take() {
+                out.set_value(Some(v));
+                stop.set(Some(Stopped::Quit(())))
+            }
+        }))
+    }
+    /// Quits without asking the [`on_before_quit`] handlers
+    pub fn force_with(&self, value: T) {
+        self.0.set_value(Some(value));
+        self.1.stop.set(Some(Stopped::Quit(())))
+    }
+    pub fn take(&self) -> Option<Stopped<T>> {
+        let stop = self.1.stop.try_update_untracked(Option::take).flatten()?;
+
+        match stop {
+            Stopped::Quit(()) => self
+                .0
+                .try_update_value(Option::take)
+                .flatten()
+                .map(Stopped::Quit),
+            Stopped::Dismissed => Some(Stopped::Dismissed),
+            Stopped::Signal(s) => Some(Stopped::Signal(s)),
+        }
+    }
+    pub fn should_quit(&self) -> bool {
+        self.1.should_quit()
+    }
+    /// Whether a quit is waiting on a deferred handler
+    pub fn is_pending(&self) -> bool {
+        self.1.is_pending()
+    }
+    /// The same quit, for code that does not know `T`
+    pub fn any(&self) -> QuitAny {
+        self.1
+    }
+}
+
+impl<T: Default + 'static> Quit<T> {
+    pub fn quit(&self) {
+        self.quit_with(T::default())
+    }
+}
+
+impl Quit<String> {
     pub fn quit_with_message(&self, msg: impl Into<String>) {
-        self.0.set(Some(msg.into()))
+        self.quit_with(msg.into())
     }
+}
+
+/// Ends the main-loop whatever the result type of the app is, which then
+/// gets [`Stopped::Dismissed`]. Provided next to every [`Quit<T>`] for
+/// library code and tests.
+#[derive(Clone, Copy)]
+pub struct QuitAny {
+    guards: QuitGuards,
+    stop: RwSignal<Option<Stopped<()>>>,
+}
+
+impl QuitAny {
+    fn attach(cx: Scope) -> Self {
+        let v = Self {
+            guards: QuitGuards::attach(cx),
+            stop: create_rw_signal(cx, None),
+        };
+        provide_context(cx, v);
+        v
+    }
+
+    /// Quits once every [`on_before_quit`] handler allows it
     pub fn quit(&self) {
-        self.0.set(Some(String::new()));
+        let stop = self.stop;
+        self.guards
+            .request(Rc::new(move || stop.set(Some(Stopped::Dismissed))))
+    }
+    /// Quits without asking the [`on_before_quit`] handlers
+    pub fn force(&self) {
+        self.stop.set(Some(Stopped::Dismissed))
     }
-    pub fn get_msg(&self) -> String {
-        self.0.get().unwrap()
+    // Signals cannot be vetoed, so no handler is asked
+    pub(crate) fn signal(&self, signal: TermSignal) {
+        self.stop.set(Some(Stopped::Signal(signal)))
     }
     pub fn should_quit(&self) -> bool {
-        self.0.get_untracked().is_some()
+        self.stop.with_untracked(Option::is_some)
+    }
+    /// Whether a quit is waiting on a deferred handler
+    pub fn is_pending(&self) -> bool {
+        self.guards.pending.with(Option::is_some)
+    }
+}
+
+pub enum BeforeQuit {
+    Allow,
+    Cancel,
+    /// Holds the quit until the [`PendingQuit`] is resumed or cancelled
+    Defer,
+}
+
+type Guard = Rc<dyn Fn(PendingQuit) -> BeforeQuit>;
+
+#[derive(Default)]
+struct Guards {
+    next: usize,
+    list: Vec<(usize, Guard)>,
+}
+
+// Not generic over the result, so handlers can be registered without it
+#[derive(Clone, Copy)]
+struct QuitGuards {
+    guards: StoredValue<Guards>,
+
+    // The attempt and the commit of the quit being asked about
+    pending: RwSignal<Option<usize>>,
+    commit: StoredValue<Option<Rc<dyn Fn()>>>,
+    attempt: StoredValue<usize>,
+}
+
+impl QuitGuards {
+    fn attach(cx: Scope) -> Self {
+        let v = Self {
+            guards: store_value(cx, Default::default()),
+            pending: create_rw_signal(cx, None),
+            commit: store_value(cx, None),
+            attempt: store_value(cx, 0),
+        };
+        provide_context(cx, v);
+        v
+    }
+
+    fn request(self, commit: Rc<dyn Fn()>) {
+        if self.pending.get_untracked().is_some() {
+            return;
+        }
+
+        self.attempt.update_value(|v| *v += 1);
+        self.commit.set_value(Some(commit));
+        self.pending.set(Some(self.attempt.get_value()));
+
+        self.run(0)
+    }
+
+    // Asks every handler registered from `from` on, in registration order
+    fn run(self, from: usize) {
+        let attempt = self.attempt.get_value();
+        let guards = self.guards.with_value(|g| {
+            g.list
+                .iter()
+                .filter(|(id, _)| *id >= from)
+                .cloned()
+                .collect::<Vec<_>>()
+        });
+
+        for (id, guard) in guards {
+            let pending = PendingQuit {
+                guards: self,
+                attempt,
+                next: id + 1,
+            };
+
+            match guard(pending) {
+                BeforeQuit::Allow => (),
+                BeforeQuit::Cancel => return self.finish(false),
+                BeforeQuit::Defer => return,
+            }
+        }
+
+        self.finish(true)
+    }
+
+    fn finish(self, quit: bool) {
+        let commit = self.commit.try_update_value(Option::take).flatten();
+        self.pending.set(None);
+
+        if let (true, Some(commit)) = (quit, commit) {
+            commit()
+        }
     }
 }
+
+/// A quit held by a [`BeforeQuit::Defer`]
+#[derive(Clone, Copy)]
+pub struct PendingQuit {
+    guards: QuitGuards,
+    attempt: usize,
+    next: usize,
+}
+
+impl PendingQuit {
+    fn is_current(&self) -> bool {
+        self.guards.pending.get_untracked() == Some(self.attempt)
+    }
+
+    /// Continues asking the remaining handlers
+    pub fn resume(self) {
+        if self.is_current() {
+            self.guards.run(self.next)
+        }
+    }
+    pub fn cancel(self) {
+        if self.is_current() {
+            self.guards.finish(false)
+        }
+    }
+}
+
+/// Registers a handler that is asked before the app quits, it is removed
+/// with `cx`
+pub fn on_before_quit(cx: Scope, handler: impl Fn(PendingQuit) -> BeforeQuit + 'static) {
+    let guards = use_context::<QuitGuards>(cx).unwrap().guards;
+
+    let id = guards
+        .try_update_value(|g| {
+            let id = g.next;
+            g.next += 1;
+            g.list.push((id, Rc::new(handler)));
+            id
+        })
+        .unwrap();
+
+    on_cleanup(cx, move || {
+        guards.try_update_value(|g| g.list.retain(|(v, _)| *v != id));
+    });
+}
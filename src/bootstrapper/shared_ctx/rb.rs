@@ -1,17 +1,55 @@
 use std::{
+    cell::{Cell, RefCell},
     convert::Infallible,
+    panic::Location,
+    rc::Rc,
     sync::{Arc, Mutex, Weak},
 };
 
-use leptos_reactive::{provide_context, Scope};
-use tui::{backend::Backend, layout::Rect, Terminal};
+use leptos_reactive::{
+    create_rw_signal, on_cleanup, provide_context, use_context, Scope, SignalGet, SignalSet,
+};
+use tui::{backend::Backend, buffer::Buffer, layout::Rect, widgets::Clear, Terminal};
+
+use crate::bootstrapper::{
+    mounts::{Mounts, Site},
+    prefix_sum_2d::PrefixSum2d,
+    sync_output::{SyncBackend, SyncOutput},
+};
 
-use crate::bootstrapper::prefix_sum_2d::PrefixSum2d;
+use super::{offscreen::Offscreen, Region};
+
+/// Widgets on higher layers are drawn on top of lower ones regardless of
+/// the order they were rendered in
+pub type Layer = u8;
+
+pub(super) enum Command {
+    Draw(Box<dyn FnOnce(&mut Buffer)>),
+    Damage(Rect, isize),
+    // Damages the whole viewport, whatever its size is by then
+    Repaint,
+}
 
 // Flushing could be scoped but this is an optimization that has to be evaluated
-pub struct RenderBase<B: Backend + 'static>(
-    pub(in super::super) Weak<Mutex<(Terminal<B>, PrefixSum2d)>>,
-);
+pub struct RenderBase<B: Backend + 'static> {
+    pub(in super::super) term: Weak<Mutex<(Terminal<B>, PrefixSum2d)>>,
+    pub(super) mounts: Rc<RefCell<Mounts>>,
+
+    // Applied to the terminal at frame time, so rendering never has to lock it
+    pub(super) commands: Rc<RefCell<Vec<(Layer, Command)>>>,
+    pub(super) layer: Layer,
+
+    // Areas are relative to and clipped by this region when set
+    pub(super) clip: Option<Region>,
+
+    // Commands are applied to this buffer instead of the terminal when set
+    pub(super) offscreen: Option<Rc<Offscreen>>,
+
+    pub(super) sync: Rc<Cell<bool>>,
+
+    // Counts frames, renders within one make up a pass of a mount
+    pub(super) pass: Rc<Cell<usize>>,
+}
 
 #[cfg(not(test))]
 #[allow(dead_code)]
@@ -27,36 +65,232 @@ pub enum RBOp<T: tui::widgets::Widget = tui::widgets::Clear> {
 
 impl<B: Backend + 'static> RenderBase<B> {
     pub fn attach(cx: Scope, v: Arc<Mutex<(Terminal<B>, PrefixSum2d)>>) {
-        let v = Self(Arc::downgrade(&v));
+        let v = Self {
+            term: Arc::downgrade(&v),
+            mounts: Default::default(),
+            commands: Default::default(),
+            layer: 0,
+            clip: None,
+            offscreen: None,
+            sync: Default::default(),
+            pass: Default::default(),
+        };
         provide_context(cx, v)
     }
 
     pub fn access(&self) -> Arc<Mutex<(Terminal<B>, PrefixSum2d)>> {
-        self.0.upgrade().unwrap()
+        self.term.upgrade().unwrap()
+    }
+
+    /// Sets whether frames are wrapped in synchronized updates
+    pub fn synchronize(&self, mode: SyncOutput) {
+        self.sync.set(mode.enabled())
+    }
+
+    /// Redraws the whole viewport from the last frame, once something else
+    /// has written to the terminal
+    pub fn repaint(&self) {
+        self.commands
+            .borrow_mut()
+            .push((self.layer, Command::Repaint));
+        self.touch();
+    }
+
+    /// Redraws every mounted component, once the frame has been lost
+    pub fn redraw_all(&self) {
+        let mounts = self.mounts.borrow().all();
+        for redraw in mounts {
+            redraw.set(());
+        }
+
+        self.repaint();
+    }
+
+    /// A handle that renders onto `layer` instead
+    pub fn layer(&self, layer: Layer) -> Self {
+        Self {
+            layer,
+            ..self.clone()
+        }
+    }
+
+    /// A handle that renders relative to the current [`Region`] of `cx` and
+    /// clips everything to its bounds
+    pub fn local(&self, cx: Scope) -> Self {
+        Self {
+            clip: use_context(cx),
+            ..self.clone()
+        }
     }
 
-    #[inline]
-    pub fn render<T: tui::widgets::Widget>(&self, widget: T, area: Rect) {
-        self.batch_render([RBOp::Component(widget, area), RBOp::Add(area)])
+    /// Renders `widget` and tracks `area` as owned by `cx`. Once `cx` is
+    /// disposed the area is cleared and whatever was rendered underneath it
+    /// is redrawn.
+    #[track_caller]
+    pub fn render<T: tui::widgets::Widget + 'static>(&self, cx: Scope, widget: T, area: Rect) {
+        let site = Location::caller();
+
+        let Some(region) = self.clip else {
+            self.mount(cx, site, area);
+            return self.batch_render([RBOp::Component(widget, area), RBOp::Add(area)]);
+        };
+
+        let region = region.get();
+        let area = translate(area, region.x, region.y);
+
+        if !area.intersects(region) {
+            let pass = self.pass.get();
+            return self.mounts.borrow_mut().skip(cx.id(), site, pass);
+        }
+
+        let clip = area.intersection(region);
+
+        self.mount(cx, site, clip);
+
+        let draw = Command::Draw(Box::new(move |buf| {
+            // Seeded from the frame so cells the widget skips stay untouched
+            let mut scratch = Buffer::empty(area);
+            if area.intersects(buf.area) {
+                blit(buf, &mut scratch, area.intersection(buf.area));
+            }
+
+            widget.render(area, &mut scratch);
+
+            if clip.intersects(buf.area) {
+                blit(&scratch, buf, clip.intersection(buf.area));
+            }
+        }));
+
+        self.commands
+            .borrow_mut()
+            .extend([(self.layer, draw), (self.layer, Command::Damage(clip, 1))]);
+        self.touch();
     }
 
-    pub fn batch_render<'a, T: tui::widgets::Widget>(
+    fn mount(&self, cx: Scope, site: Site, area: Rect) {
+        let pass = self.pass.get();
+        let tracked = self
+            .mounts
+            .borrow_mut()
+            .track(cx.id(), site, area, self.layer, pass);
+
+        let redraw = match tracked {
+            Some(v) => v,
+            None => {
+                let redraw = create_rw_signal(cx, ());
+                self.mounts
+                    .borrow_mut()
+                    .mount(cx.ancestry(), site, area, self.layer, pass, redraw);
+
+                let mounts = Rc::downgrade(&self.mounts);
+                on_cleanup(cx, move || {
+                    if let Some(mounts) = mounts.upgrade() {
+                        mounts.borrow_mut().unmount(cx.id());
+                    }
+                });
+
+                redraw
+            }
+        };
+
+        // Subscribes the rendering effect so it can be re-run on damage
+        redraw.get();
+    }
+
+    /// Clears the areas of disposed scopes and redraws anything they covered
+    pub(super) fn unmount(&self) {
+        let unmounted = self.mounts.borrow_mut().take_unmounted();
+
+        if unmounted.is_empty() {
+            return;
+        }
+
+        // Cleared before anything rendered this frame so new mounts survive
+        let clears = unmounted
+            .iter()
+            .flat_map(|&a| [RBOp::Component(Clear, a), RBOp::Add(a)])
+            .map(|op| (0, Self::command(op)))
+            .collect::<Vec<_>>();
+        self.commands.borrow_mut().splice(0..0, clears);
+
+        let damaged = self.mounts.borrow().damaged(&unmounted);
+        for redraw in damaged {
+            redraw.set(());
+        }
+    }
+
+    pub fn batch_render<T: tui::widgets::Widget + 'static>(
         &self,
         ops: impl IntoIterator<Item = RBOp<T>>,
     ) {
-        let upgrade = self.access();
-        let mut term = upgrade.try_lock().unwrap();
-
-        for op in ops {
-            match op {
-                RBOp::Component(w, a) => term.0.get_frame().render_widget(w, a),
-                RBOp::Add(a) => term.1.insert(a),
-                RBOp::Sub(a) => term.1.insert_mul(a, -1),
+        self.commands
+            .borrow_mut()
+            .extend(ops.into_iter().map(|op| (self.layer, Self::command(op))));
+        self.touch();
+    }
+
+    // Lets an offscreen owner know it has to be flushed and blit again
+    fn touch(&self) {
+        if let Some(offscreen) = &self.offscreen {
+            if !offscreen.flushing.get() {
+                offscreen.dirty.set(());
             }
         }
     }
 
+    fn command<T: tui::widgets::Widget + 'static>(op: RBOp<T>) -> Command {
+        match op {
+            RBOp::Component(w, a) => Command::Draw(Box::new(move |buf| w.render(a, buf))),
+            RBOp::Add(a) => Command::Damage(a, 1),
+            RBOp::Sub(a) => Command::Damage(a, -1),
+        }
+    }
+
+    /// Applies the queued commands to the terminal buffer, lowest layer first
+    pub(in super::super) fn flush(&self) {
+        let mut commands = std::mem::take(&mut *self.commands.borrow_mut());
+
+        if commands.is_empty() {
+            return;
+        }
+
+        commands.sort_by_key(|(layer, _)| *layer);
+
+        if let Some(offscreen) = &self.offscreen {
+            let buf = &mut *offscreen.buffer.borrow_mut();
+
+            // The whole offscreen buffer is damaged once it is blit
+            for (_, command) in commands {
+                if let Command::Draw(draw) = command {
+                    draw(buf)
+                }
+            }
+
+            return;
+        }
+
+        let term = self.access();
+        let term = &mut *term.try_lock().unwrap();
+
+        for (_, command) in commands {
+            match command {
+                Command::Draw(draw) => draw(term.0.current_buffer_mut()),
+                Command::Damage(a, mul) => term.1.insert_mul(a, mul),
+                Command::Repaint => {
+                    let area = term.1.area();
+                    term.1.insert_mul(area, 1)
+                }
+            }
+        }
+    }
+}
+
+impl<B: SyncBackend + 'static> RenderBase<B> {
     pub fn do_frame(&self) -> Result<(), std::io::Error> {
+        self.unmount();
+        self.flush();
+        self.pass.set(self.pass.get() + 1);
+
         let term = self.access();
         let term = &mut term.try_lock().unwrap();
 
@@ -67,10 +301,22 @@ impl<B: Backend + 'static> RenderBase<B> {
             .content
             .iter()
             .zip(base_data.iter())
-            .filter_map(|(cell, (x, y, v))| (v > 0).then_some((x, y, cell)));
+            .filter_map(|(cell, (x, y, v))| (v > 0).then_some((x, y, cell)))
+            .collect::<Vec<_>>();
 
-        term.0.backend_mut().draw(data)?;
-        term.0.backend_mut().flush()?;
+        if !data.is_empty() {
+            let backend = term.0.backend_mut();
+            let sync = self.sync.get();
+
+            if sync {
+                backend.begin_sync()?;
+            }
+            backend.draw(data.into_iter())?;
+            if sync {
+                backend.end_sync()?;
+            }
+            backend.flush()?;
+        }
         term.1.clear();
 
         Ok(())
@@ -79,6 +325,37 @@ impl<B: Backend + 'static> RenderBase<B> {
 
 impl<B: Backend> Clone for RenderBase<B> {
     fn clone(&self) -> Self {
-        Self(Weak::clone(&self.0))
+        Self {
+            term: Weak::clone(&self.term),
+            mounts: Rc::clone(&self.mounts),
+            commands: Rc::clone(&self.commands),
+            layer: self.layer,
+            clip: self.clip,
+            offscreen: self.offscreen.clone(),
+            sync: Rc::clone(&self.sync),
+            pass: Rc::clone(&self.pass),
+        }
+    }
+}
+
+/// Moves `area` by `(dx, dy)`, cut off where the coordinates run out as
+/// nested regions and offsets can add up past them
+pub(super) fn translate(area: Rect, dx: u16, dy: u16) -> Rect {
+    let (x, y) = (area.x.saturating_add(dx), area.y.saturating_add(dy));
+
+    Rect::new(
+        x,
+        y,
+        std::cmp::min(area.width, u16::MAX - x),
+        std::cmp::min(area.height, u16::MAX - y),
+    )
+}
+
+/// Copies the cells within `area` from one buffer to the other
+pub(super) fn blit(from: &Buffer, to: &mut Buffer, area: Rect) {
+    for y in area.top()..area.bottom() {
+        for x in area.left()..area.right() {
+            *to.get_mut(x, y) = from.get(x, y).clone();
+        }
     }
 }
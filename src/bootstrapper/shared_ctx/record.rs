@@ -0,0 +1,363 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    error::Error,
+    fs::{self, File},
+    io::{self, LineWriter, Write},
+    path::Path,
+    rc::Rc,
+    time::Duration,
+};
+
+use crossterm::event::Event;
+use leptos_reactive::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    super::test_bootstrap, Clock, Console, EventDispatcher, EventQueue, Region, RenderCounter,
+};
+
+// The first line of a recording
+#[derive(Serialize, Deserialize)]
+struct Header {
+    width: u16,
+    height: u16,
+}
+
+// Every other line, `at` in milliseconds since the recording started
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    at: u64,
+    event: Event,
+}
+
+/// Input recorded by [`record`], one JSON object per line. The first line is
+/// the size of the terminal, every other line an event and when it happened.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Session {
+    pub size: (u16, u16),
+    pub events: Vec<(Duration, Event)>,
+}
+
+impl Session {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Replays the session in a [`test_bootstrap`] the size of the terminal
+    /// it was recorded in, `boot` runs after the replay is set up
+    pub fn test_bootstrap(
+        self,
+        speed: f64,
+        boot: impl FnOnce(Scope, Replay) + 'static,
+        once: Option<usize>,
+    ) -> Result<(), Box<dyn Error>> {
+        let (w, h) = self.size;
+
+        test_bootstrap(
+            move |cx| {
+                let replay = replay(cx, self, speed);
+                boot(cx, replay)
+            },
+            w,
+            h,
+            once,
+        )
+    }
+
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut lines = text.lines().filter(|v| !v.trim().is_empty());
+
+        let header: Header = match lines.next() {
+            Some(line) => serde_json::from_str(line)?,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Empty recording",
+                ))
+            }
+        };
+
+        let events = lines
+            .map(|line| {
+                let e: Entry = serde_json::from_str(line)?;
+                Ok((Duration::from_millis(e.at), e.event))
+            })
+            .collect::<io::Result<_>>()?;
+
+        Ok(Self {
+            size: (header.width, header.height),
+            events,
+        })
+    }
+}
+
+/// Writes every event from the [`EventQueue`] to `path` until `cx` is
+/// disposed, timed by the [`Clock`]. Lines are written as they happen, so a
+/// crash keeps everything up to it.
+pub fn record(cx: Scope, path: impl AsRef<Path>) -> io::Result<()> {
+    let mut out = LineWriter::new(File::create(path)?);
+
+    let region: Region = use_context(cx).unwrap();
+    let r = region.get_untracked();
+    serde_json::to_writer(
+        &mut out,
+        &Header {
+            width: r.width,
+            height: r.height,
+        },
+    )?;
+    writeln!(out)?;
+
+    let eq: EventQueue = use_context(cx).unwrap();
+    let clock = use_context::<Clock>(cx).unwrap();
+    let console = use_context::<Console>(cx);
+    let start = clock.now();
+
+    let out = RefCell::new(Some(out));
+    eq.listen(cx, move |event| {
+        let mut out = out.borrow_mut();
+        let Some(w) = out.as_mut() else { return };
+
+        let entry = Entry {
+            at: clock.now().saturating_sub(start).as_millis() as u64,
+            event: event.clone(),
+        };
+        let res = serde_json::to_writer(&mut *w, &entry)
+            .map_err(io::Error::from)
+            .and_then(|_| writeln!(w));
+
+        // Stops at the first failure instead of logging every event
+        if let Err(e) = res {
+            *out = None;
+            if let Some(console) = console {
+                console.log(format!("Recording stopped: {}", e));
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// The slowest [`replay`], a second of the session taking about 11 days
+pub const MIN_SPEED: f64 = 1e-6;
+
+/// A [`Session`] being fed back through the [`EventDispatcher`]
+#[derive(Clone, Copy)]
+pub struct Replay {
+    pub finished: ReadSignal<bool>,
+}
+
+/// Dispatches the events of `session` at their recorded times, `speed` times
+/// faster, with `f64::INFINITY` not waiting at all, anything not positive
+/// counting as 1 and speeds below [`MIN_SPEED`] raised to it. A virtual
+/// [`Clock`] is moved forward to each event instead, one event per frame, so
+/// tests replay deterministically at any speed.
+pub fn replay(cx: Scope, session: Session, speed: f64) -> Replay {
+    // Also rules out NaN
+    let speed = if speed > 0.0 {
+        speed.max(MIN_SPEED)
+    } else {
+        1.0
+    };
+
+    let ed = use_context::<EventDispatcher>(cx).unwrap();
+    let rc: RenderCounter = use_context(cx).unwrap();
+    let clock = use_context::<Clock>(cx).unwrap();
+
+    let finished = create_rw_signal(cx, session.events.is_empty());
+    let queue = Rc::new(RefCell::new(VecDeque::from(session.events)));
+    let start = clock.now();
+
+    create_effect(cx, move |_| {
+        rc.0.get();
+
+        let mut queue = queue.borrow_mut();
+
+        // A frame after the last dispatch, once it has been handled
+        if queue.is_empty() {
+            if !finished.get_untracked() {
+                finished.set(true);
+            }
+            return;
+        }
+
+        while let Some((at, _)) = queue.front() {
+            // Times past what a `Duration` holds are never reached anyway
+            let due = Duration::try_from_secs_f64(at.as_secs_f64() / speed)
+                .map_or(Duration::MAX, |at| start.saturating_add(at));
+            let now = clock.now();
+
+            match &clock {
+                Clock::Virtual(_) if due > now => clock.advance(due - now),
+                Clock::System(_) if due > now => break,
+                _ => (),
+            }
+
+            let (_, e) = queue.pop_front().unwrap();
+            if ed.dispatch(e).is_err() {
+                queue.clear();
+            }
+
+            if let Clock::Virtual(_) = &clock {
+                break;
+            }
+        }
+    });
+
+    Replay {
+        finished: finished.read_only(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, fs, rc::Rc, time::Duration};
+
+    use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+    use leptos_reactive::*;
+    use tui::layout::Rect;
+
+    use crate::bootstrapper::{
+        shared_ctx::{Clock, EventDispatcher, EventQueue, QuitAny, Region, RenderCounter},
+        test_bootstrap,
+    };
+
+    use super::{record, replay, Session};
+
+    #[test]
+    fn replays_a_recording() {
+        let path = std::env::temp_dir().join(format!("x-tui-session-{}.jsonl", std::process::id()));
+        let file = path.clone();
+
+        let key = Event::Key(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE));
+        let paste = Event::Paste("hi".into());
+        let resize = Event::Resize(8, 6);
+        let events = [key.clone(), paste.clone(), resize.clone()];
+
+        test_bootstrap(
+            move |cx| {
+                record(cx, &file).unwrap();
+
+                let ed = use_context::<EventDispatcher>(cx).unwrap();
+                let rc = use_context::<RenderCounter>(cx).unwrap();
+                let clock = use_context::<Clock>(cx).unwrap();
+
+                create_effect(cx, move |_| {
+                    let step = rc.0.get();
+                    if let Some(e) = events.get(step) {
+                        clock.advance(Duration::from_millis(100 * step as u64));
+                        ed.dispatch(e.clone()).unwrap();
+                    }
+                });
+            },
+            10,
+            8,
+            Some(4),
+        )
+        .unwrap();
+
+        let session = Session::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let ms = Duration::from_millis;
+        assert_eq!(
+            session,
+            Session {
+                size: (10, 8),
+                events: vec![
+                    (ms(0), key.clone()),
+                    (ms(100), paste.clone()),
+                    (ms(300), resize.clone())
+                ],
+            }
+        );
+
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let inner = Rc::clone(&log);
+
+        let session = Session {
+            size: (9, 7),
+            ..session
+        };
+        session
+            .test_bootstrap(
+                2.0,
+                move |cx, replay| {
+                    let eq = use_context::<EventQueue>(cx).unwrap();
+                    let clock = use_context::<Clock>(cx).unwrap();
+                    let quit = use_context::<QuitAny>(cx).unwrap();
+                    let region = use_context::<Region>(cx).unwrap();
+                    assert_eq!(region.get_untracked(), Rect::new(0, 0, 9, 7));
+
+                    eq.listen(cx, move |e| {
+                        inner.borrow_mut().push((clock.now(), e.clone()))
+                    });
+
+                    create_effect(cx, move |_| {
+                        if replay.finished.get() {
+                            quit.quit()
+                        }
+                    });
+                },
+                Some(20),
+            )
+            .unwrap();
+
+        assert_eq!(
+            *log.borrow(),
+            [(ms(0), key), (ms(50), paste), (ms(150), resize)]
+        );
+
+        // Speeds that would not move time forward play as recorded
+        let session = Session {
+            size: (10, 8),
+            events: vec![(ms(200), Event::FocusLost)],
+        };
+        test_bootstrap(
+            move |cx| {
+                let clock = use_context::<Clock>(cx).unwrap();
+                let quit = use_context::<QuitAny>(cx).unwrap();
+
+                let replay = replay(cx, session, f64::NAN);
+                create_effect(cx, move |_| {
+                    if replay.finished.get() {
+                        assert_eq!(clock.now(), ms(200));
+                        quit.quit()
+                    }
+                });
+            },
+            10,
+            8,
+            Some(5),
+        )
+        .unwrap();
+
+        // Tiny speeds are raised instead of overflowing the wait
+        let session = Session {
+            size: (10, 8),
+            events: vec![
+                (ms(200), Event::FocusLost),
+                (ms(u64::MAX), Event::FocusLost),
+            ],
+        };
+        test_bootstrap(
+            move |cx| {
+                let clock = use_context::<Clock>(cx).unwrap();
+                let quit = use_context::<QuitAny>(cx).unwrap();
+
+                let replay = replay(cx, session, 1e-300);
+                create_effect(cx, move |_| {
+                    if replay.finished.get() {
+                        assert_eq!(clock.now(), Duration::MAX);
+                        quit.quit()
+                    }
+                });
+            },
+            10,
+            8,
+            Some(5),
+        )
+        .unwrap();
+    }
+}
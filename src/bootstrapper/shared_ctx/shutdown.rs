@@ -0,0 +1,40 @@
+use leptos_reactive::*;
+
+type Hook = Box<dyn FnOnce()>;
+
+/// Cleanup run once the main-loop has stopped, before the terminal is
+/// restored
+#[derive(Clone, Copy)]
+pub struct Shutdown(StoredValue<Vec<(usize, Hook)>>, StoredValue<usize>);
+
+impl Shutdown {
+    pub fn attach(cx: Scope) -> Self {
+        let v = Self(store_value(cx, Vec::new()), store_value(cx, 0));
+        provide_context(cx, v);
+        v
+    }
+
+    /// Runs the hooks in reverse registration order, so anything registered
+    /// later is cleaned up before what it depends on
+    pub fn run(&self) {
+        let hooks = self.0.try_update_value(std::mem::take).unwrap_or_default();
+
+        for (_, hook) in hooks.into_iter().rev() {
+            hook()
+        }
+    }
+}
+
+/// Registers `hook` to run on shutdown, it is dropped if `cx` is disposed
+/// before that
+pub fn on_shutdown(cx: Scope, hook: impl FnOnce() + 'static) {
+    let shutdown = use_context::<Shutdown>(cx).unwrap();
+
+    let id = shutdown.1.get_value();
+    shutdown.1.set_value(id + 1);
+    shutdown.0.update_value(|v| v.push((id, Box::new(hook))));
+
+    on_cleanup(cx, move || {
+        shutdown.0.try_update_value(|v| v.retain(|(v, _)| *v != id));
+    });
+}
@@ -0,0 +1,169 @@
+use std::{
+    fmt::Display,
+    io,
+    process::ExitCode,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use leptos_reactive::*;
+use signal_hook::{consts, flag, low_level, SigId};
+
+/// A request from outside to end the process
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TermSignal {
+    Interrupt,
+    Terminate,
+    Hangup,
+}
+
+impl TermSignal {
+    const ALL: [TermSignal; 3] = [Self::Interrupt, Self::Terminate, Self::Hangup];
+
+    pub fn number(self) -> i32 {
+        match self {
+            Self::Interrupt => consts::SIGINT,
+            Self::Terminate => consts::SIGTERM,
+            Self::Hangup => consts::SIGHUP,
+        }
+    }
+
+    /// The exit code of a shell job ended by the signal
+    pub fn exit_code(self) -> ExitCode {
+        ExitCode::from(128 + self.number() as u8)
+    }
+}
+
+impl Display for TermSignal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Interrupt => "SIGINT",
+            Self::Terminate => "SIGTERM",
+            Self::Hangup => "SIGHUP",
+        };
+        write!(f, "Stopped by {}", name)
+    }
+}
+
+/// The last [`TermSignal`] received, the main-loop stops right after it is
+/// set
+#[derive(Clone, Copy)]
+pub struct TermSignals(pub ReadSignal<Option<TermSignal>>);
+
+impl std::ops::Deref for TermSignals {
+    type Target = ReadSignal<Option<TermSignal>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+// Removing a handler does not bring back the default action, so one that runs
+// it is registered once and switched on while no watch is alive
+struct Defaults {
+    live: usize,
+    restore: Option<Arc<AtomicBool>>,
+}
+
+static DEFAULTS: Mutex<Defaults> = Mutex::new(Defaults {
+    live: 0,
+    restore: None,
+});
+
+/// Catches the signals for as long as it is alive, the default actions are
+/// back once it is dropped
+pub struct SignalWatch {
+    flag: Arc<AtomicUsize>,
+    ids: Vec<SigId>,
+    received: RwSignal<Option<TermSignal>>,
+}
+
+impl TermSignals {
+    pub fn attach(cx: Scope) -> io::Result<SignalWatch> {
+        let received = create_rw_signal(cx, None);
+        provide_context(cx, Self(received.read_only()));
+
+        let mut defaults = DEFAULTS.lock().unwrap();
+        if defaults.restore.is_none() {
+            let restore = Arc::new(AtomicBool::new(true));
+            for s in TermSignal::ALL {
+                flag::register_conditional_default(s.number(), Arc::clone(&restore))?;
+            }
+            defaults.restore = Some(restore);
+        }
+
+        let flag = Arc::new(AtomicUsize::new(0));
+        let ids = TermSignal::ALL
+            .iter()
+            .map(|s| flag::register_usize(s.number(), Arc::clone(&flag), s.number() as usize))
+            .collect::<io::Result<_>>()?;
+
+        defaults.live += 1;
+        if let Some(restore) = &defaults.restore {
+            restore.store(false, Ordering::SeqCst);
+        }
+
+        Ok(SignalWatch {
+            flag,
+            ids,
+            received,
+        })
+    }
+}
+
+impl SignalWatch {
+    /// Publishes a signal received since the last call
+    pub fn poll(&self) -> Option<TermSignal> {
+        let number = self.flag.swap(0, Ordering::SeqCst) as i32;
+        let signal = TermSignal::ALL.into_iter().find(|s| s.number() == number)?;
+
+        self.received.set(Some(signal));
+        Some(signal)
+    }
+}
+
+impl Drop for SignalWatch {
+    fn drop(&mut self) {
+        for id in self.ids.drain(..) {
+            low_level::unregister(id);
+        }
+
+        let mut defaults = DEFAULTS.lock().unwrap();
+        defaults.live -= 1;
+        if let (0, Some(restore)) = (defaults.live, &defaults.restore) {
+            restore.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use leptos_reactive::{create_runtime, create_scope, use_context, SignalGetUntracked};
+    use signal_hook::low_level;
+    use std::sync::atomic::Ordering;
+
+    use super::{TermSignal, TermSignals, DEFAULTS};
+
+    #[test]
+    fn signals_are_published() {
+        create_scope(create_runtime(), |cx| {
+            let watch = TermSignals::attach(cx).unwrap();
+            let signals = use_context::<TermSignals>(cx).unwrap();
+
+            assert_eq!(watch.poll(), None);
+
+            low_level::raise(TermSignal::Hangup.number()).unwrap();
+
+            assert_eq!(watch.poll(), Some(TermSignal::Hangup));
+            assert_eq!(signals.get_untracked(), Some(TermSignal::Hangup));
+            assert_eq!(watch.poll(), None);
+
+            drop(watch);
+            let defaults = DEFAULTS.lock().unwrap();
+            assert!(defaults.restore.as_ref().unwrap().load(Ordering::SeqCst));
+        })
+        .dispose();
+    }
+}
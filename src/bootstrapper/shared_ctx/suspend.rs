@@ -0,0 +1,147 @@
+use std::{
+    io,
+    process::{Command, ExitStatus},
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use leptos_reactive::*;
+use signal_hook::{consts, flag, low_level};
+
+type Handoff = Rc<dyn Fn() -> io::Result<()>>;
+
+/// Hands the terminal to the shell or another program and takes it back
+#[derive(Clone)]
+pub struct Suspend {
+    release: Handoff,
+    acquire: Handoff,
+
+    // Set by SIGCONT, also when the shell stopped the process
+    continued: Arc<AtomicBool>,
+}
+
+impl Suspend {
+    /// `acquire` has to redraw the whole frame, anything may have been
+    /// written over it in the meantime
+    pub fn attach(
+        cx: Scope,
+        release: impl Fn() -> io::Result<()> + 'static,
+        acquire: impl Fn() -> io::Result<()> + 'static,
+    ) -> io::Result<Self> {
+        let continued = Arc::new(AtomicBool::new(false));
+        let id = flag::register(consts::SIGCONT, Arc::clone(&continued))?;
+        on_cleanup(cx, move || {
+            low_level::unregister(id);
+        });
+
+        let v = Self {
+            release: Rc::new(release),
+            acquire: Rc::new(acquire),
+            continued,
+        };
+        provide_context(cx, v.clone());
+        Ok(v)
+    }
+
+    /// Stops the process like a shell job, returning once it is continued
+    pub fn suspend(&self) -> io::Result<()> {
+        (self.release)()?;
+
+        #[cfg(unix)]
+        low_level::raise(consts::SIGTSTP)?;
+
+        // Taken back here already, not again by `poll`
+        self.continued.store(false, Ordering::SeqCst);
+        (self.acquire)()
+    }
+
+    /// Takes the terminal back if the process was continued after being
+    /// stopped from outside, e.g. by `kill -TSTP`, checked by the main-loop
+    pub fn poll(&self) -> io::Result<()> {
+        match self.continued.swap(false, Ordering::SeqCst) {
+            true => (self.acquire)(),
+            false => Ok(()),
+        }
+    }
+
+    /// Runs `command` in the terminal, e.g. an editor, and waits for it
+    pub fn run_external(&self, command: &mut Command) -> io::Result<ExitStatus> {
+        (self.release)()?;
+        let status = command.status();
+        (self.acquire)()?;
+
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::RefCell,
+        process::Command,
+        rc::Rc,
+        sync::{atomic::AtomicBool, Arc},
+    };
+
+    use leptos_reactive::{create_runtime, create_scope};
+    use signal_hook::{consts, flag, low_level};
+
+    use super::Suspend;
+
+    #[test]
+    fn external_gets_the_terminal() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let (release, acquire) = (Rc::clone(&log), Rc::clone(&log));
+
+        create_scope(create_runtime(), move |cx| {
+            let suspend = Suspend::attach(
+                cx,
+                move || Ok(release.borrow_mut().push("release")),
+                move || Ok(acquire.borrow_mut().push("acquire")),
+            )
+            .unwrap();
+
+            let status = suspend.run_external(&mut Command::new("true")).unwrap();
+            assert!(status.success());
+
+            // The terminal is taken back even if the program never ran
+            assert!(suspend.run_external(&mut Command::new("")).is_err());
+        })
+        .dispose();
+
+        assert_eq!(*log.borrow(), ["release", "acquire", "release", "acquire"]);
+    }
+
+    #[test]
+    fn suspend_and_continue() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let (release, acquire) = (Rc::clone(&log), Rc::clone(&log));
+
+        // Caught, so the test process is not actually stopped
+        let stopped = Arc::new(AtomicBool::new(false));
+        let id = flag::register(consts::SIGTSTP, Arc::clone(&stopped)).unwrap();
+
+        create_scope(create_runtime(), move |cx| {
+            let suspend = Suspend::attach(
+                cx,
+                move || Ok(release.borrow_mut().push("release")),
+                move || Ok(acquire.borrow_mut().push("acquire")),
+            )
+            .unwrap();
+
+            suspend.suspend().unwrap();
+            low_level::raise(consts::SIGCONT).unwrap();
+            suspend.poll().unwrap();
+            suspend.poll().unwrap();
+        })
+        .dispose();
+
+        low_level::unregister(id);
+
+        assert!(stopped.load(std::sync::atomic::Ordering::SeqCst));
+        assert_eq!(*log.borrow(), ["release", "acquire", "acquire"]);
+    }
+}
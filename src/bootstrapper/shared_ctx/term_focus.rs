@@ -0,0 +1,89 @@
+use crossterm::event::Event;
+use leptos_reactive::*;
+
+use super::EventQueue;
+
+/// Whether the terminal window has focus. Terminals that do not report focus
+/// changes always count as focused.
+///
+/// The main-loop ticks the [`RenderCounter`](super::RenderCounter) at most
+/// every 250ms while unfocused, so timers driven by it, like keymap timeouts,
+/// config reloads, long presses and replays, run late instead of stalling.
+#[derive(Clone, Copy)]
+pub struct TerminalFocused(pub ReadSignal<bool>);
+
+impl std::ops::Deref for TerminalFocused {
+    type Target = ReadSignal<bool>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl TerminalFocused {
+    pub fn attach(cx: Scope) -> Self {
+        let focused = create_rw_signal(cx, true);
+        let v = Self(focused.read_only());
+        provide_context(cx, v);
+
+        let eq: EventQueue = use_context(cx).unwrap();
+        create_effect(cx, move |_| {
+            let now = match eq.get() {
+                Event::FocusGained => true,
+                Event::FocusLost => false,
+                _ => return,
+            };
+
+            if focused.get_untracked() != now {
+                focused.set(now);
+            }
+        });
+
+        v
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use crossterm::event::Event;
+    use leptos_reactive::*;
+
+    use crate::bootstrapper::{
+        shared_ctx::{EventDispatcher, RenderCounter},
+        test_bootstrap,
+    };
+
+    use super::TerminalFocused;
+
+    #[test]
+    fn follows_focus_events() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let inner = Rc::clone(&log);
+
+        test_bootstrap(
+            move |cx| {
+                let ed = use_context::<EventDispatcher>(cx).unwrap();
+                let rc = use_context::<RenderCounter>(cx).unwrap();
+                let focused = use_context::<TerminalFocused>(cx).unwrap();
+
+                create_effect(cx, move |_| inner.borrow_mut().push(focused.get()));
+
+                create_effect(cx, move |_| {
+                    if rc.0.get() == 0 {
+                        for e in [Event::FocusLost, Event::FocusLost, Event::FocusGained] {
+                            ed.dispatch(e).unwrap();
+                        }
+                    }
+                });
+            },
+            10,
+            10,
+            Some(4),
+        )
+        .unwrap();
+
+        assert_eq!(*log.borrow(), [true, false, true]);
+    }
+}
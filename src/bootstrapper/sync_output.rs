@@ -0,0 +1,73 @@
+use std::io::{self, Write};
+
+use tui::backend::{Backend, CrosstermBackend, TestBackend};
+
+// DEC private mode 2026, terminals without support ignore it
+const BEGIN: &[u8] = b"\x1b[?2026h";
+const END: &[u8] = b"\x1b[?2026l";
+
+/// Whether frames are wrapped in synchronized updates, so the terminal
+/// presents them all at once instead of tearing mid-draw
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SyncOutput {
+    Off,
+    On,
+    #[default]
+    Auto,
+}
+
+impl SyncOutput {
+    pub fn enabled(self) -> bool {
+        match self {
+            SyncOutput::Off => false,
+            SyncOutput::On => true,
+            SyncOutput::Auto => detect(),
+        }
+    }
+}
+
+// There is no query for mode 2026 that does not race the event reader, so
+// known terminals are picked out of the environment instead
+fn detect() -> bool {
+    let var = |k: &str| std::env::var(k).unwrap_or_default().to_lowercase();
+
+    let term = var("TERM");
+    let program = var("TERM_PROGRAM");
+
+    if term == "linux" || term == "dumb" {
+        return false;
+    }
+
+    std::env::var_os("KITTY_WINDOW_ID").is_some()
+        || std::env::var_os("WEZTERM_EXECUTABLE").is_some()
+        || std::env::var_os("ALACRITTY_WINDOW_ID").is_some()
+        || std::env::var_os("WT_SESSION").is_some()
+        || ["kitty", "foot", "alacritty", "contour", "wezterm"]
+            .iter()
+            .any(|v| term.contains(v))
+        || ["iterm.app", "wezterm", "vscode", "ghostty", "tabby"].contains(&program.as_str())
+}
+
+/// Backends that can mark the start and end of a frame
+pub trait SyncBackend: Backend {
+    fn begin_sync(&mut self) -> io::Result<()>;
+    fn end_sync(&mut self) -> io::Result<()>;
+}
+
+impl<W: Write> SyncBackend for CrosstermBackend<W> {
+    fn begin_sync(&mut self) -> io::Result<()> {
+        self.write_all(BEGIN)
+    }
+    fn end_sync(&mut self) -> io::Result<()> {
+        self.write_all(END)
+    }
+}
+
+impl SyncBackend for TestBackend {
+    fn begin_sync(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+    fn end_sync(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
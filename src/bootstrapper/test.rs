@@ -5,7 +5,7 @@ use std::{
 };
 use tui::{backend::TestBackend, Terminal};
 
-use super::{bootstrap, init::TestInit, prefix_sum_2d::PrefixSum2d};
+use super::{bootstrap, init::TestInit, prefix_sum_2d::PrefixSum2d, sync_output::SyncBackend};
 
 pub fn test_bootstrap(
     boot: impl FnOnce(Scope) + 'static,
@@ -13,7 +13,14 @@ pub fn test_bootstrap(
     h: u16,
     once: Option<usize>,
 ) -> Result<(), Box<dyn Error>> {
-    let backend = TestBackend::new(w, h);
+    test_bootstrap_backend(boot, TestBackend::new(w, h), once)
+}
+
+pub fn test_bootstrap_backend<B: SyncBackend + 'static>(
+    boot: impl FnOnce(Scope) + 'static,
+    backend: B,
+    once: Option<usize>,
+) -> Result<(), Box<dyn Error>> {
     let terminal = Terminal::new(backend)?;
     let ps = PrefixSum2d::new(terminal.size()?);
 
@@ -29,10 +36,13 @@ pub fn test_bootstrap(
 use super::shared_ctx::RenderBase;
 #[cfg(test)]
 pub fn assert_rb(rb: &RenderBase<TestBackend>, snap_name: &'_ str) {
+    rb.flush();
+
     let term = rb.access();
     let mut term = term.try_lock().unwrap();
 
-    let rect = term.0.size().unwrap();
+    // The viewport, which differs from the backend once resized
+    let rect = *term.0.current_buffer_mut().area();
 
     let mut symbols = term
         .0
@@ -54,3 +64,110 @@ pub fn assert_rb(rb: &RenderBase<TestBackend>, snap_name: &'_ str) {
 
     insta::assert_snapshot!(snap_name, out.as_str());
 }
+
+/// A mouse event without modifiers
+#[cfg(test)]
+pub fn mouse(
+    kind: crossterm::event::MouseEventKind,
+    column: u16,
+    row: u16,
+) -> crossterm::event::Event {
+    crossterm::event::Event::Mouse(crossterm::event::MouseEvent {
+        kind,
+        column,
+        row,
+        modifiers: crossterm::event::KeyModifiers::NONE,
+    })
+}
+
+/// Stand-in for a real terminal that records the bytes written to it
+#[cfg(test)]
+pub struct RecordingBackend {
+    inner: tui::backend::CrosstermBackend<Recording>,
+    size: tui::layout::Rect,
+}
+
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub struct Recording(Arc<Mutex<Vec<u8>>>, Arc<std::sync::atomic::AtomicBool>);
+
+#[cfg(test)]
+impl Recording {
+    pub fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+    /// Makes every write after this fail
+    pub fn fail(&self) {
+        self.1.store(true, std::sync::atomic::Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+impl std::io::Write for Recording {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.1.load(std::sync::atomic::Ordering::SeqCst) {
+            return Err(std::io::ErrorKind::BrokenPipe.into());
+        }
+        self.0.lock().unwrap().write(buf)
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl RecordingBackend {
+    pub fn new(w: u16, h: u16) -> (Self, Recording) {
+        let rec = Recording::default();
+        let inner = tui::backend::CrosstermBackend::new(rec.clone());
+
+        (
+            Self {
+                inner,
+                size: tui::layout::Rect::new(0, 0, w, h),
+            },
+            rec,
+        )
+    }
+}
+
+#[cfg(test)]
+impl tui::backend::Backend for RecordingBackend {
+    fn draw<'a, I>(&mut self, content: I) -> std::io::Result<()>
+    where
+        I: Iterator<Item = (u16, u16, &'a tui::buffer::Cell)>,
+    {
+        self.inner.draw(content)
+    }
+    fn hide_cursor(&mut self) -> std::io::Result<()> {
+        self.inner.hide_cursor()
+    }
+    fn show_cursor(&mut self) -> std::io::Result<()> {
+        self.inner.show_cursor()
+    }
+    fn get_cursor(&mut self) -> std::io::Result<(u16, u16)> {
+        Ok((0, 0))
+    }
+    fn set_cursor(&mut self, x: u16, y: u16) -> std::io::Result<()> {
+        self.inner.set_cursor(x, y)
+    }
+    fn clear(&mut self) -> std::io::Result<()> {
+        self.inner.clear()
+    }
+    fn size(&self) -> std::io::Result<tui::layout::Rect> {
+        Ok(self.size)
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        tui::backend::Backend::flush(&mut self.inner)
+    }
+}
+
+#[cfg(test)]
+impl SyncBackend for RecordingBackend {
+    fn begin_sync(&mut self) -> std::io::Result<()> {
+        self.inner.begin_sync()
+    }
+    fn end_sync(&mut self) -> std::io::Result<()> {
+        self.inner.end_sync()
+    }
+}
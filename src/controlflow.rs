@@ -113,7 +113,7 @@ mod tests {
     use super::*;
     use crate::bootstrapper::{
         assert_rb,
-        shared_ctx::{Quit, RenderBaseAuto, RenderCounter},
+        shared_ctx::{QuitAny, RenderBaseAuto, RenderCounter},
         test_bootstrap,
     };
 
@@ -125,7 +125,7 @@ mod tests {
                 let cycle: RenderCounter = use_context(cx).unwrap();
 
                 let rb: RenderBaseAuto = use_context(cx).unwrap();
-                let quit: Quit = use_context(cx).unwrap();
+                let quit: QuitAny = use_context(cx).unwrap();
 
                 each(
                     cx,
@@ -138,7 +138,7 @@ mod tests {
                             let v = v();
                             let p = Paragraph::new(v.0.to_string());
 
-                            rb.render(p, v.1);
+                            rb.render(cx, p, v.1);
                         })
                     },
                 );
@@ -165,4 +165,46 @@ mod tests {
         )
         .unwrap();
     }
+
+    #[test]
+    fn show_clears_on_unmount() {
+        test_bootstrap(
+            |cx| {
+                let when = create_rw_signal(cx, true);
+                let cycle: RenderCounter = use_context(cx).unwrap();
+
+                let rb: RenderBaseAuto = use_context(cx).unwrap();
+
+                let base = rb.clone();
+                create_effect(cx, move |_| {
+                    base.render(cx, Paragraph::new("base"), Rect::new(0, 0, 10, 2));
+                });
+
+                show(
+                    cx,
+                    when,
+                    |cx| {
+                        let rb: RenderBaseAuto = use_context(cx).unwrap();
+                        create_effect(cx, move |_| {
+                            rb.render(cx, Paragraph::new("top"), Rect::new(0, 1, 10, 2));
+                        })
+                    },
+                    |_| {},
+                );
+
+                create_effect(cx, move |_| match cycle.0() {
+                    0 => {
+                        assert_rb(&rb, "show-unmount-0");
+                        when.set(false);
+                    }
+                    1 => assert_rb(&rb, "show-unmount-1"),
+                    _ => (),
+                })
+            },
+            10,
+            4,
+            Some(2),
+        )
+        .unwrap();
+    }
 }
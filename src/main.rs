@@ -4,18 +4,18 @@ mod controlflow;
 mod split_word_wrap;
 mod tdom;
 
-use bootstrapper::{app_bootstrap, shared_ctx::*};
+use bootstrapper::{app_bootstrap, exit_with, shared_ctx::*};
 use crossterm::event::{Event, KeyCode};
 use leptos_reactive::{create_effect, create_rw_signal, prelude::*, use_context};
-use std::error::Error;
+use std::process::ExitCode;
 use tdom::*;
 use tui::{
     text::Span,
     widgets::{BorderType, Borders, Clear, Paragraph},
 };
 
-fn main() -> Result<(), Box<dyn Error>> {
-    app_bootstrap(|cx| {
+fn main() -> ExitCode {
+    let res = app_bootstrap(|cx| {
         let quit: Quit = use_context(cx).unwrap();
         let event: EventQueue = use_context(cx).unwrap();
         let sz: Region = use_context(cx).unwrap();
@@ -45,19 +45,30 @@ fn main() -> Result<(), Box<dyn Error>> {
         //     })
         // });
 
+        on_mouse_leave(cx, move || cons.log("Pointer left"));
+
         create_effect(cx, move |_| match event() {
             Event::Key(e) => match e.code {
                 KeyCode::Char(c) => str.update(|v| v.push(c)),
                 KeyCode::Enter => quit.quit_with_message("Hello World"),
-                KeyCode::Up => term0.render(Clear, sz()),
+                KeyCode::Up => term0.render(cx, Clear, sz()),
                 KeyCode::Backspace => str.update(|v| {
                     v.pop();
                 }),
                 KeyCode::Down => cons.log("Down pressed\n\n\nhi\nhi"),
                 _ => (),
             },
+            // One update for the whole paste, and its newlines do not submit
+            Event::Paste(text) => {
+                str.update(|v| v.extend(text.chars().filter(|c| !c.is_control())))
+            }
             _ => {}
         })
+    });
+
+    exit_with(res, |stopped: Stopped<String>| match stopped.value() {
+        Some(msg) if !msg.is_empty() => println!("{}", msg),
+        _ => (),
     })
 }
 // fn main() -> Result<(), Box<dyn Error>> {
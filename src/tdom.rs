@@ -21,7 +21,7 @@ pub struct Clearing;
 
 impl<B: Backend> Render<B> for Clearing {
     fn render(self, cx: Scope, area: Rect, base: &RenderBase<B>) {
-        base.render(Clear, area)
+        base.render(cx, Clear, area)
     }
 }
 
@@ -68,7 +68,7 @@ impl<B: Backend, Child: Render<B>, Title: Clone + for<'a> Into<Spans<'a>>> Rende
 
         let inner = MaybeSignal::derive(cx, move || block.with(|v| v.inner(area)));
 
-        create_effect(cx, move |_| base.render(block(), area));
+        create_effect(cx, move |_| base.render(cx, block(), area));
     }
 }
 